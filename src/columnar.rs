@@ -0,0 +1,227 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use arrow::array::{
+    ArrayRef, Float64Array, StringArray, TimestampSecondArray, UInt32Array, UInt8Array,
+};
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::record_batch::RecordBatch;
+
+use crate::types::{DiveLog, PressureFill};
+
+/// File format for the dive and sample tables.
+#[derive(Debug, Clone, Copy)]
+pub enum TableFormat {
+    Parquet,
+    ArrowIpc,
+}
+
+impl TableFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            TableFormat::Parquet => "parquet",
+            TableFormat::ArrowIpc => "arrow",
+        }
+    }
+}
+
+fn strings<'a>(values: impl Iterator<Item = Option<&'a str>>) -> ArrayRef {
+    Arc::new(values.collect::<StringArray>())
+}
+
+fn floats(values: impl Iterator<Item = Option<f64>>) -> ArrayRef {
+    Arc::new(values.collect::<Float64Array>())
+}
+
+/// One row per dive with the summary fields of `DiveLog`.
+fn dives_table(dives: &[DiveLog]) -> Result<RecordBatch> {
+    let timestamp = DataType::Timestamp(TimeUnit::Second, None);
+    let timestamp_utc = DataType::Timestamp(TimeUnit::Second, Some("UTC".into()));
+    let schema = Schema::new(vec![
+        Field::new("number", DataType::UInt32, false),
+        Field::new("source", DataType::Utf8, true),
+        Field::new("start", timestamp, false),
+        Field::new("start_utc", timestamp_utc, true),
+        Field::new("timezone", DataType::Utf8, true),
+        Field::new("duration_s", DataType::UInt32, false),
+        Field::new("max_depth_m", DataType::Float64, false),
+        Field::new("min_temp_c", DataType::Float64, true),
+        Field::new("max_temp_c", DataType::Float64, true),
+        Field::new("dive_mode", DataType::Utf8, false),
+        Field::new("o2_percent", DataType::UInt8, true),
+        Field::new("start_pressure_bar", DataType::Float64, true),
+        Field::new("end_pressure_bar", DataType::Float64, true),
+        Field::new("site", DataType::Utf8, true),
+        Field::new("country", DataType::Utf8, true),
+        Field::new("buddy", DataType::Utf8, true),
+        Field::new("notes", DataType::Utf8, true),
+        Field::new("dive_type", DataType::Utf8, true),
+        Field::new("water_body", DataType::Utf8, true),
+        Field::new("water_type", DataType::Utf8, true),
+        Field::new("visibility_m", DataType::Float64, true),
+        Field::new("weather", DataType::Utf8, true),
+        Field::new("air_temp_c", DataType::Float64, true),
+        Field::new("entry", DataType::Utf8, true),
+        Field::new("weight_kg", DataType::Float64, true),
+        Field::new("rating", DataType::UInt8, true),
+        Field::new("computer_model", DataType::Utf8, true),
+        Field::new("computer_serial", DataType::Utf8, true),
+    ]);
+
+    // Start / end pressure of the first tank with readings
+    let pressures: Vec<Option<(f64, f64)>> = dives
+        .iter()
+        .map(|d| d.tank_pressures().first().map(|&(_, start, end)| (start, end)))
+        .collect();
+    let modes: Vec<String> = dives
+        .iter()
+        .map(|d| format!("{:?}", d.dive_mode).to_lowercase())
+        .collect();
+    let timezones: Vec<Option<String>> =
+        dives.iter().map(|d| d.timezone.map(|tz| tz.to_string())).collect();
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(dives.iter().map(|d| d.number).collect::<UInt32Array>()),
+        strings(dives.iter().map(|d| d.source.as_deref())),
+        Arc::new(
+            dives
+                .iter()
+                .map(|d| Some(d.start().0.and_utc().timestamp()))
+                .collect::<TimestampSecondArray>(),
+        ),
+        Arc::new(
+            dives
+                .iter()
+                .map(|d| d.utc_offset().map(|_| d.start_utc().and_utc().timestamp()))
+                .collect::<TimestampSecondArray>()
+                .with_timezone("UTC"),
+        ),
+        strings(timezones.iter().map(|tz| tz.as_deref())),
+        Arc::new(dives.iter().map(|d| d.duration_seconds).collect::<UInt32Array>()),
+        floats(dives.iter().map(|d| Some(d.max_depth_m))),
+        floats(dives.iter().map(|d| d.temp_range().map(|(min, _)| min))),
+        floats(dives.iter().map(|d| d.temp_range().map(|(_, max)| max))),
+        strings(modes.iter().map(|m| Some(m.as_str()))),
+        Arc::new(
+            dives
+                .iter()
+                .map(|d| d.gas_mixes.first().map(|g| g.o2))
+                .collect::<UInt8Array>(),
+        ),
+        floats(pressures.iter().map(|p| p.map(|(start, _)| start))),
+        floats(pressures.iter().map(|p| p.map(|(_, end)| end))),
+        strings(dives.iter().map(|d| d.site.as_deref())),
+        strings(dives.iter().map(|d| d.country.as_deref())),
+        strings(dives.iter().map(|d| d.buddy.as_deref())),
+        strings(dives.iter().map(|d| d.notes.as_deref())),
+        strings(dives.iter().map(|d| d.details.dive_type.as_deref())),
+        strings(dives.iter().map(|d| d.details.water_body.as_deref())),
+        strings(dives.iter().map(|d| d.details.water_type.as_deref())),
+        floats(dives.iter().map(|d| d.details.visibility_m)),
+        strings(dives.iter().map(|d| d.details.weather.as_deref())),
+        floats(dives.iter().map(|d| d.details.air_temp_c)),
+        strings(dives.iter().map(|d| d.details.entry.as_deref())),
+        floats(dives.iter().map(|d| d.details.weight_kg)),
+        Arc::new(dives.iter().map(|d| d.details.rating).collect::<UInt8Array>()),
+        strings(dives.iter().map(|d| d.computer.as_ref().map(|c| c.model.as_str()))),
+        strings(
+            dives
+                .iter()
+                .map(|d| d.computer.as_ref().and_then(|c| c.serial.as_deref())),
+        ),
+    ];
+
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+/// One row per sample, keyed by dive number. Pressure is a single
+/// `pressure_bar` column when no dive has more than one tank, otherwise one
+/// `pressure_tN_bar` column per tank slot (as in the CSV export).
+fn samples_table(dives: &[DiveLog], fill: PressureFill) -> Result<RecordBatch> {
+    let pressures: Vec<Vec<(u8, Vec<Option<f64>>)>> =
+        dives.iter().map(|d| d.sample_pressures(fill)).collect();
+    let multi_tank = pressures.iter().any(|p| p.len() > 1);
+    let mut tanks: Vec<u8> = pressures
+        .iter()
+        .flatten()
+        .map(|(tank, _)| *tank)
+        .collect();
+    tanks.sort_unstable();
+    tanks.dedup();
+
+    let rows = || dives.iter().flat_map(|d| d.samples.iter().map(move |s| (d, s)));
+
+    let mut fields = vec![
+        Field::new("dive_number", DataType::UInt32, false),
+        Field::new("time_s", DataType::UInt32, false),
+        Field::new("depth_m", DataType::Float64, false),
+        Field::new("temp_c", DataType::Float64, true),
+    ];
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(rows().map(|(d, _)| d.number).collect::<UInt32Array>()),
+        Arc::new(rows().map(|(_, s)| s.time_s).collect::<UInt32Array>()),
+        floats(rows().map(|(_, s)| Some(s.depth_m))),
+        floats(rows().map(|(_, s)| s.temp_c)),
+    ];
+
+    // Pressure of `tank` per sample row, null where the dive has no such tank
+    let tank_column = |tank: Option<u8>| {
+        floats(dives.iter().zip(&pressures).flat_map(move |(dive, series)| {
+            let values = series
+                .iter()
+                .find(|(t, _)| tank.is_none_or(|tank| *t == tank))
+                .map(|(_, v)| v);
+            (0..dive.samples.len()).map(move |i| values.and_then(|v| v[i]))
+        }))
+    };
+    if multi_tank {
+        for &tank in &tanks {
+            fields.push(Field::new(
+                format!("pressure_t{}_bar", tank + 1),
+                DataType::Float64,
+                true,
+            ));
+            columns.push(tank_column(Some(tank)));
+        }
+    } else {
+        fields.push(Field::new("pressure_bar", DataType::Float64, true));
+        columns.push(tank_column(None));
+    }
+
+    Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)?)
+}
+
+fn write_batch(batch: &RecordBatch, path: &Path, format: TableFormat) -> Result<()> {
+    let file = std::fs::File::create(path)?;
+    match format {
+        TableFormat::Parquet => {
+            let mut writer = parquet::arrow::ArrowWriter::try_new(file, batch.schema(), None)?;
+            writer.write(batch)?;
+            writer.close()?;
+        }
+        TableFormat::ArrowIpc => {
+            let mut writer = arrow::ipc::writer::FileWriter::try_new(file, &batch.schema())?;
+            writer.write(batch)?;
+            writer.finish()?;
+        }
+    }
+    Ok(())
+}
+
+/// Write `{stem}_dives.{ext}` and `{stem}_samples.{ext}` next to `output`.
+pub fn write_tables(
+    dives: &[DiveLog],
+    output: &Path,
+    fill: PressureFill,
+    format: TableFormat,
+) -> Result<Vec<PathBuf>> {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let dir = output.parent().unwrap_or(Path::new("."));
+    let dives_path = dir.join(format!("{}_dives.{}", stem, format.extension()));
+    let samples_path = dir.join(format!("{}_samples.{}", stem, format.extension()));
+
+    write_batch(&dives_table(dives)?, &dives_path, format)?;
+    write_batch(&samples_table(dives, fill)?, &samples_path, format)?;
+    Ok(vec![dives_path, samples_path])
+}
@@ -0,0 +1,160 @@
+use crate::types::{DiveLog, DiveMode, PressureFill};
+
+/// HL7 encoding characters that follow every DL7 header segment name.
+const ENCODING: &str = "^~<>{}";
+
+/// Escape characters that have a meaning in DL7 fields.
+fn field(text: &str) -> String {
+    text.chars()
+        .map(|c| if "|^~<>{}\r\n".contains(c) { ' ' } else { c })
+        .collect()
+}
+
+fn opt<T>(value: Option<T>, fmt: impl Fn(T) -> String) -> String {
+    value.map(fmt).unwrap_or_default()
+}
+
+/// Recording interval code (e.g. "Q5S") from the sample spacing.
+fn interval_code(dive: &DiveLog) -> String {
+    let interval = match dive.samples.as_slice() {
+        [a, b, ..] => b.time_s.saturating_sub(a.time_s).max(1),
+        _ => 5,
+    };
+    format!("Q{}S", interval)
+}
+
+/// Build a DAN DL7 file with one ZDH/ZDP/ZDT block per dive. Site, country
+/// and buddy go into the application block (`ZAR`).
+pub fn dives_to_dl7(dives: &[DiveLog]) -> String {
+    let mut out = String::new();
+    let mut line = |s: String| {
+        out.push_str(&s);
+        out.push_str("\r\n");
+    };
+
+    let now = chrono::Utc::now().format("%Y%m%d%H%M%S");
+    line(format!(
+        "FSH|{}|{}^{}^|ZXU|{}|",
+        ENCODING,
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        now
+    ));
+    let computer = dives.iter().find_map(|d| d.computer.as_ref());
+    line(format!(
+        "ZRH|{}|{}|{}|MSWG|ThM|C|bar|L|",
+        ENCODING,
+        opt(computer, |dc| field(&dc.model)),
+        opt(computer.and_then(|dc| dc.serial.as_deref()), field),
+    ));
+    let described: Vec<&DiveLog> = dives
+        .iter()
+        .filter(|d| d.site.is_some() || d.country.is_some() || d.buddy.is_some())
+        .collect();
+    if described.is_empty() {
+        line("ZAR{}".to_string());
+    } else {
+        line("ZAR{".to_string());
+        for dive in described {
+            line(format!(
+                "{}|{}|{}|{}|",
+                dive.number,
+                opt(dive.site.as_deref(), field),
+                opt(dive.country.as_deref(), field),
+                opt(dive.buddy.as_deref(), field),
+            ));
+        }
+        line("ZAR}".to_string());
+    }
+
+    for (seq, dive) in dives.iter().enumerate() {
+        let seq = seq + 1;
+        let (start, _) = dive.start();
+        let end = start + chrono::Duration::seconds(dive.duration_seconds as i64);
+        let gas = dive.gas_mixes.first();
+        let o2_mode = match dive.dive_mode {
+            DiveMode::Nitrox => "FO2",
+            _ => "AIR",
+        };
+
+        line(format!(
+            "ZDH|{}|{}|I|{}|{}|||{}|",
+            seq,
+            dive.number,
+            interval_code(dive),
+            start.format("%Y%m%d%H%M%S"),
+            o2_mode,
+        ));
+
+        // Profile rows: time (min), depth, gas (fO2 at the start), ..., water
+        // temperature, ..., main cylinder pressure
+        let pressures = dive.sample_pressures(PressureFill::None);
+        let main_tank = pressures.first().map(|(_, values)| values);
+        line("ZDP{".to_string());
+        for (i, sample) in dive.samples.iter().enumerate() {
+            let gas_switch = if i == 0 {
+                opt(gas, |g| format!("{:.2}", g.o2 as f64 / 100.0))
+            } else {
+                String::new()
+            };
+            line(format!(
+                "|{:.3}|{:.1}|{}|||||{}||{}|",
+                sample.time_s as f64 / 60.0,
+                sample.depth_m,
+                gas_switch,
+                opt(sample.temp_c, |t| format!("{:.1}", t)),
+                opt(main_tank.and_then(|v| v[i]), |p| format!("{:.0}", p)),
+            ));
+        }
+        line("ZDP}".to_string());
+
+        line(format!(
+            "ZDT|{}|{}|{:.1}|{}|{}|",
+            seq,
+            dive.number,
+            dive.max_depth_m,
+            end.format("%Y%m%d%H%M%S"),
+            opt(dive.temp_range(), |(min, _)| format!("{:.1}", min)),
+        ));
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_and_profile_lines() {
+        let text = dives_to_dl7(&[DiveLog::sample()]);
+        let lines: Vec<&str> = text.split("\r\n").collect();
+        assert!(lines[0].starts_with("FSH|^~<>{}|sirius-dive^"));
+        assert_eq!(lines[1], "ZRH|^~<>{}|Sirius|123456|MSWG|ThM|C|bar|L|");
+        assert_eq!(lines[2], "ZAR{}");
+        assert_eq!(lines[3], "ZDH|1|12|I|Q10S|20250304101500|||FO2|");
+        assert_eq!(lines[4], "ZDP{");
+        assert_eq!(lines[5], "|0.000|0.0|0.32|||||27.0||200|");
+        assert_eq!(lines[6], "|0.167|12.3||||||26.5||190|");
+        assert_eq!(lines[7], "|0.333|0.0||||||26.5|||");
+        assert_eq!(lines[8], "ZDP}");
+    }
+
+    #[test]
+    fn anonymized_dives_leave_out_personal_fields() {
+        let mut dive = DiveLog {
+            site: Some("Blue Corner".to_string()),
+            country: Some("Palau".to_string()),
+            buddy: Some("Jane Doe".to_string()),
+            notes: Some("Manta at the cleaning station".to_string()),
+            ..DiveLog::sample()
+        };
+        dive.anonymize();
+
+        let text = dives_to_dl7(&[dive]);
+        let lines: Vec<&str> = text.split("\r\n").collect();
+        assert_eq!(lines[1], "ZRH|^~<>{}|Sirius||MSWG|ThM|C|bar|L|");
+        assert_eq!(lines[2], "ZAR{}");
+        assert!(!text.contains("123456"));
+    }
+}
@@ -0,0 +1,462 @@
+use crate::types::{DiveLog, DiveMode};
+
+/// Seconds between the Unix epoch and the FIT epoch (1989-12-31 00:00 UTC).
+const FIT_EPOCH_OFFSET: i64 = 631_065_600;
+
+/// FIT profile version the messages follow (21.40).
+const PROFILE_VERSION: u16 = 2140;
+
+// Global message numbers
+const MESG_FILE_ID: u16 = 0;
+const MESG_SESSION: u16 = 18;
+const MESG_LAP: u16 = 19;
+const MESG_RECORD: u16 = 20;
+const MESG_EVENT: u16 = 21;
+const MESG_ACTIVITY: u16 = 34;
+const MESG_DIVE_GAS: u16 = 259;
+const MESG_DIVE_SUMMARY: u16 = 268;
+
+// Common field numbers
+const FIELD_TIMESTAMP: u8 = 253;
+const FIELD_MESSAGE_INDEX: u8 = 254;
+
+const SPORT_DIVING: u8 = 53;
+const MANUFACTURER_DEVELOPMENT: u16 = 255;
+
+/// A field value with its FIT base type. Optional values are written as the
+/// base type's invalid value.
+enum Value {
+    Enum(u8),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U32z(u32),
+    S8(Option<i8>),
+    Str(String),
+}
+
+impl Value {
+    fn base_type(&self) -> u8 {
+        match self {
+            Value::Enum(_) => 0x00,
+            Value::U8(_) => 0x02,
+            Value::U16(_) => 0x84,
+            Value::U32(_) => 0x86,
+            Value::U32z(_) => 0x8C,
+            Value::S8(_) => 0x01,
+            Value::Str(_) => 0x07,
+        }
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match self {
+            Value::Enum(v) | Value::U8(v) => vec![*v],
+            Value::U16(v) => v.to_le_bytes().to_vec(),
+            Value::U32(v) | Value::U32z(v) => v.to_le_bytes().to_vec(),
+            Value::S8(v) => vec![v.unwrap_or(i8::MAX) as u8],
+            Value::Str(s) => {
+                // The field size is one byte, including the terminating NUL
+                let mut end = s.len().min(254);
+                while !s.is_char_boundary(end) {
+                    end -= 1;
+                }
+                let mut b = s.as_bytes()[..end].to_vec();
+                b.push(0);
+                b
+            }
+        }
+    }
+}
+
+/// Running FIT CRC-16 over `data`, as specified by the FIT protocol.
+fn crc16(mut crc: u16, data: &[u8]) -> u16 {
+    const TABLE: [u16; 16] = [
+        0x0000, 0xCC01, 0xD801, 0x1400, 0xF001, 0x3C00, 0x2800, 0xE401, 0xA001, 0x6C00, 0x7800,
+        0xB401, 0x5000, 0x9C01, 0x8801, 0x4400,
+    ];
+    for &byte in data {
+        for nibble in [byte & 0x0F, byte >> 4] {
+            let tmp = TABLE[(crc & 0x0F) as usize];
+            crc = (crc >> 4) & 0x0FFF;
+            crc = crc ^ tmp ^ TABLE[nibble as usize];
+        }
+    }
+    crc
+}
+
+/// Writes data messages, emitting a definition message whenever a message
+/// layout has not been defined yet. Layouts are assigned to the 16 local
+/// message types round robin.
+struct FitWriter {
+    data: Vec<u8>,
+    /// Layout (global number, field number / size / base type) per local type.
+    locals: Vec<Vec<u8>>,
+    next_local: usize,
+}
+
+impl FitWriter {
+    fn new() -> Self {
+        FitWriter {
+            data: Vec::new(),
+            locals: Vec::new(),
+            next_local: 0,
+        }
+    }
+
+    fn message(&mut self, global: u16, fields: &[(u8, Value)]) {
+        let values: Vec<Vec<u8>> = fields.iter().map(|(_, v)| v.bytes()).collect();
+        let mut layout = global.to_le_bytes().to_vec();
+        for ((num, value), bytes) in fields.iter().zip(&values) {
+            layout.extend([*num, bytes.len() as u8, value.base_type()]);
+        }
+
+        let local = match self.locals.iter().position(|l| *l == layout) {
+            Some(local) => local,
+            None => {
+                let local = self.next_local;
+                self.next_local = (self.next_local + 1) % 16;
+                // Definition: header, reserved, little endian, global number,
+                // field count, field definitions
+                self.data.push(0x40 | local as u8);
+                self.data.extend([0, 0]);
+                self.data.extend(global.to_le_bytes());
+                self.data.push(fields.len() as u8);
+                self.data.extend(&layout[2..]);
+                if local < self.locals.len() {
+                    self.locals[local] = layout;
+                } else {
+                    self.locals.push(layout);
+                }
+                local
+            }
+        };
+
+        self.data.push(local as u8);
+        for bytes in values {
+            self.data.extend(bytes);
+        }
+    }
+
+    /// The complete file: 14-byte header, records and the file CRC.
+    fn finish(self) -> Vec<u8> {
+        let mut out = vec![14, 0x20];
+        out.extend(PROFILE_VERSION.to_le_bytes());
+        out.extend((self.data.len() as u32).to_le_bytes());
+        out.extend(b".FIT");
+        let header_crc = crc16(0, &out);
+        out.extend(header_crc.to_le_bytes());
+        out.extend(self.data);
+        let crc = crc16(0, &out);
+        out.extend(crc.to_le_bytes());
+        out
+    }
+}
+
+/// FIT `sub_sport` for the dive mode.
+fn sub_sport(dive: &DiveLog) -> u8 {
+    match dive.dive_mode {
+        DiveMode::Freedive => 56,
+        DiveMode::Gauge => 55,
+        DiveMode::Air | DiveMode::Nitrox if dive.gas_mixes.len() > 1 => 54,
+        DiveMode::Air | DiveMode::Nitrox => 53,
+    }
+}
+
+/// Encode one dive as a FIT diving activity: file_id, dive_gas per gas mix,
+/// a record per sample with depth and temperature, then lap, session (each
+/// with a dive_summary) and activity.
+pub fn dive_to_fit(dive: &DiveLog) -> Vec<u8> {
+    let start_unix = dive.start_utc().and_utc().timestamp();
+    let fit_time = |offset_s: u32| (start_unix - FIT_EPOCH_OFFSET + offset_s as i64) as u32;
+    let start = fit_time(0);
+    let end = fit_time(dive.duration_seconds);
+    let millis = |seconds: u32| seconds.saturating_mul(1000);
+    let temp = |t: Option<f64>| t.map(|t| t.round().clamp(-127.0, 126.0) as i8);
+
+    let mut fit = FitWriter::new();
+
+    let mut file_id = vec![
+        (0, Value::Enum(4)), // activity
+        (1, Value::U16(MANUFACTURER_DEVELOPMENT)),
+        (4, Value::U32(start)),
+    ];
+    if let Some(dc) = &dive.computer {
+        if let Some(serial) = dc.serial.as_deref().and_then(|s| s.parse().ok()) {
+            file_id.push((3, Value::U32z(serial)));
+        }
+        file_id.push((8, Value::Str(dc.model.clone())));
+    }
+    fit.message(MESG_FILE_ID, &file_id);
+
+    // Timer start
+    fit.message(
+        MESG_EVENT,
+        &[(FIELD_TIMESTAMP, Value::U32(start)), (0, Value::Enum(0)), (1, Value::Enum(0))],
+    );
+
+    if matches!(dive.dive_mode, DiveMode::Air | DiveMode::Nitrox) {
+        for (i, gas) in dive.gas_mixes.iter().enumerate() {
+            fit.message(
+                MESG_DIVE_GAS,
+                &[
+                    (FIELD_MESSAGE_INDEX, Value::U16(i as u16)),
+                    (0, Value::U8(0)),      // helium
+                    (1, Value::U8(gas.o2)), // oxygen
+                    (2, Value::Enum(1)),    // enabled
+                    (3, Value::Enum(0)),    // open circuit
+                ],
+            );
+        }
+    }
+
+    for sample in &dive.samples {
+        fit.message(
+            MESG_RECORD,
+            &[
+                (FIELD_TIMESTAMP, Value::U32(fit_time(sample.time_s))),
+                (92, Value::U32((sample.depth_m * 1000.0).round() as u32)),
+                (13, Value::S8(temp(sample.temp_c))),
+            ],
+        );
+    }
+
+    // Timer stop all
+    fit.message(
+        MESG_EVENT,
+        &[(FIELD_TIMESTAMP, Value::U32(end)), (0, Value::Enum(0)), (1, Value::Enum(4))],
+    );
+
+    let dive_summary = |fit: &mut FitWriter, reference_mesg: u16| {
+        fit.message(
+            MESG_DIVE_SUMMARY,
+            &[
+                (FIELD_TIMESTAMP, Value::U32(end)),
+                (0, Value::U16(reference_mesg)),
+                (1, Value::U16(0)), // reference_index
+                (2, Value::U32(dive.avg_depth().map_or(u32::MAX, |d| (d * 1000.0).round() as u32))),
+                (3, Value::U32((dive.max_depth_m * 1000.0).round() as u32)),
+                (10, Value::U32(dive.number)),
+                (11, Value::U32(millis(dive.duration_seconds))), // bottom_time
+            ],
+        );
+    };
+
+    fit.message(
+        MESG_LAP,
+        &[
+            (FIELD_TIMESTAMP, Value::U32(end)),
+            (FIELD_MESSAGE_INDEX, Value::U16(0)),
+            (2, Value::U32(start)),
+            (7, Value::U32(millis(dive.duration_seconds))),
+            (8, Value::U32(millis(dive.duration_seconds))),
+            (0, Value::Enum(9)), // lap
+            (1, Value::Enum(1)), // stop
+            (25, Value::Enum(SPORT_DIVING)),
+            (39, Value::Enum(sub_sport(dive))),
+        ],
+    );
+    dive_summary(&mut fit, MESG_LAP);
+
+    let temps: Vec<f64> = dive.samples.iter().filter_map(|s| s.temp_c).collect();
+    let avg_temp = (!temps.is_empty()).then(|| temps.iter().sum::<f64>() / temps.len() as f64);
+    fit.message(
+        MESG_SESSION,
+        &[
+            (FIELD_TIMESTAMP, Value::U32(end)),
+            (FIELD_MESSAGE_INDEX, Value::U16(0)),
+            (2, Value::U32(start)),
+            (7, Value::U32(millis(dive.duration_seconds))),
+            (8, Value::U32(millis(dive.duration_seconds))),
+            (0, Value::Enum(8)), // session
+            (1, Value::Enum(1)), // stop
+            (5, Value::Enum(SPORT_DIVING)),
+            (6, Value::Enum(sub_sport(dive))),
+            (25, Value::U16(0)), // first_lap_index
+            (26, Value::U16(1)), // num_laps
+            (57, Value::S8(temp(avg_temp))),
+            (58, Value::S8(temp(dive.temp_range().map(|(_, max)| max)))),
+        ],
+    );
+    dive_summary(&mut fit, MESG_SESSION);
+
+    let local_end = dive.start().0 + chrono::Duration::seconds(dive.duration_seconds as i64);
+    fit.message(
+        MESG_ACTIVITY,
+        &[
+            (FIELD_TIMESTAMP, Value::U32(end)),
+            (0, Value::U32(millis(dive.duration_seconds))),
+            (1, Value::U16(1)),  // num_sessions
+            (2, Value::Enum(0)), // manual
+            (3, Value::Enum(26)), // activity
+            (4, Value::Enum(1)), // stop
+            (5, Value::U32((local_end.and_utc().timestamp() - FIT_EPOCH_OFFSET) as u32)),
+        ],
+    );
+
+    fit.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::GasMix;
+
+    /// A decoded data message: global number and (field number, raw bytes).
+    struct Message {
+        global: u16,
+        fields: Vec<(u8, Vec<u8>)>,
+    }
+
+    impl Message {
+        fn field(&self, num: u8) -> &[u8] {
+            let (_, bytes) = self.fields.iter().find(|(n, _)| *n == num).expect("missing field");
+            bytes
+        }
+
+        fn u8(&self, num: u8) -> u8 {
+            self.field(num)[0]
+        }
+
+        fn i8(&self, num: u8) -> i8 {
+            self.field(num)[0] as i8
+        }
+
+        fn u16(&self, num: u8) -> u16 {
+            u16::from_le_bytes(self.field(num).try_into().unwrap())
+        }
+
+        fn u32(&self, num: u8) -> u32 {
+            u32::from_le_bytes(self.field(num).try_into().unwrap())
+        }
+    }
+
+    /// Global number and (field number, size) of a local message type.
+    type Definition = (u16, Vec<(u8, usize)>);
+
+    /// Minimal FIT decoder: checks both CRCs and that every data message uses
+    /// a defined local type, and returns the data messages in file order.
+    fn decode(file: &[u8]) -> Vec<Message> {
+        assert_eq!(file[0], 14, "header size");
+        assert_eq!(&file[8..12], b".FIT");
+        let header_crc = u16::from_le_bytes([file[12], file[13]]);
+        assert_eq!(header_crc, crc16(0, &file[..12]), "header CRC");
+        let data_size = u32::from_le_bytes(file[4..8].try_into().unwrap()) as usize;
+        assert_eq!(file.len(), 14 + data_size + 2, "data size");
+        let (body, crc) = file.split_at(file.len() - 2);
+        assert_eq!(u16::from_le_bytes([crc[0], crc[1]]), crc16(0, body), "file CRC");
+
+        let mut locals: [Option<Definition>; 16] = Default::default();
+        let mut messages = Vec::new();
+        let mut pos = 14;
+        while pos < body.len() {
+            let header = body[pos];
+            assert_eq!(header & 0xA0, 0, "compressed header or developer fields");
+            let local = (header & 0x0F) as usize;
+            pos += 1;
+            if header & 0x40 != 0 {
+                assert_eq!(body[pos + 1], 0, "little endian");
+                let global = u16::from_le_bytes([body[pos + 2], body[pos + 3]]);
+                let count = body[pos + 4] as usize;
+                pos += 5;
+                let fields = body[pos..pos + 3 * count]
+                    .chunks(3)
+                    .map(|f| (f[0], f[1] as usize))
+                    .collect();
+                pos += 3 * count;
+                locals[local] = Some((global, fields));
+            } else {
+                let (global, layout) = locals[local].as_ref().expect("undefined local type");
+                let mut fields = Vec::new();
+                for &(num, size) in layout {
+                    fields.push((num, body[pos..pos + size].to_vec()));
+                    pos += size;
+                }
+                messages.push(Message { global: *global, fields });
+            }
+        }
+        assert_eq!(pos, body.len());
+        messages
+    }
+
+    /// Two gases and a sample without temperature.
+    fn nitrox_dive() -> DiveLog {
+        let mut dive = DiveLog {
+            timezone: Some("+07:00".parse().unwrap()),
+            gas_mixes: vec![GasMix { o2: 32 }, GasMix { o2: 50 }],
+            ..DiveLog::sample()
+        };
+        dive.samples[2].temp_c = None;
+        dive
+    }
+
+    #[test]
+    fn round_trip() {
+        let dive = nitrox_dive();
+        let messages = decode(&dive_to_fit(&dive));
+        let of = |global| messages.iter().filter(move |m| m.global == global);
+        // 2025-03-04 03:15:00 UTC
+        let start = (1_741_058_100 - FIT_EPOCH_OFFSET) as u32;
+
+        let file_id = of(MESG_FILE_ID).next().unwrap();
+        assert_eq!(file_id.u32(4), start);
+        assert_eq!(file_id.u32(3), 123456);
+        assert_eq!(file_id.field(8), b"Sirius\0");
+
+        let o2: Vec<u8> = of(MESG_DIVE_GAS).map(|m| m.u8(1)).collect();
+        assert_eq!(o2, [32, 50]);
+
+        let records: Vec<_> = of(MESG_RECORD).collect();
+        assert_eq!(records.len(), 3);
+        for (record, sample) in records.iter().zip(&dive.samples) {
+            assert_eq!(record.u32(FIELD_TIMESTAMP), start + sample.time_s);
+            assert_eq!(record.u32(92), (sample.depth_m * 1000.0) as u32);
+        }
+        let temps: Vec<i8> = records.iter().map(|r| r.i8(13)).collect();
+        assert_eq!(temps, [27, 27, i8::MAX]);
+
+        let lap = of(MESG_LAP).next().unwrap();
+        assert_eq!(lap.u32(2), start);
+        assert_eq!(lap.u32(7), 20_000);
+        assert_eq!(lap.u8(39), 54); // multi-gas diving
+
+        let session = of(MESG_SESSION).next().unwrap();
+        assert_eq!(session.u32(2), start);
+        assert_eq!(session.u32(7), 20_000);
+        assert_eq!(session.u8(5), SPORT_DIVING);
+        assert_eq!(session.i8(57), 27); // 26.75 rounded
+        assert_eq!(session.i8(58), 27);
+
+        let summaries: Vec<_> = of(MESG_DIVE_SUMMARY).collect();
+        let refs: Vec<u16> = summaries.iter().map(|s| s.u16(0)).collect();
+        assert_eq!(refs, [MESG_LAP, MESG_SESSION]);
+        for summary in summaries {
+            assert_eq!(summary.u32(3), 12_300);
+            assert_eq!(summary.u32(10), 12);
+            assert_eq!(summary.u32(FIELD_TIMESTAMP), start + 20);
+        }
+    }
+
+    #[test]
+    fn long_strings_are_truncated_on_a_char_boundary() {
+        let bytes = Value::Str("é".repeat(200)).bytes();
+        assert_eq!(bytes.len(), 255);
+        assert_eq!(bytes.last(), Some(&0));
+        assert!(std::str::from_utf8(&bytes[..254]).is_ok());
+
+        let bytes = Value::Str("a".to_string() + &"é".repeat(200)).bytes();
+        assert_eq!(bytes.len(), 254);
+    }
+
+    #[test]
+    fn local_types_are_redefined_when_reused() {
+        // More than 16 layouts: local types are reassigned round robin
+        let mut fit = FitWriter::new();
+        for num in 0..20u8 {
+            fit.message(MESG_RECORD, &[(num, Value::U8(num))]);
+        }
+        fit.message(MESG_RECORD, &[(0, Value::U8(0))]);
+        let messages = decode(&fit.finish());
+        assert_eq!(messages.len(), 21);
+        assert_eq!(messages[20].u8(0), 0);
+    }
+}
@@ -0,0 +1,160 @@
+use crate::types::{Apnea, Sample};
+
+/// Depth at which an apnea starts.
+const APNEA_START_M: f64 = 1.0;
+/// Depth above which an apnea ends. The gap to `APNEA_START_M` keeps surface
+/// chop around 1 m from splitting one apnea into several.
+const APNEA_END_M: f64 = 0.5;
+
+/// Split a freedive session into individual apneas: runs of samples that go
+/// below `APNEA_START_M` and last until the diver is back above
+/// `APNEA_END_M`. Each apnea starts at the last surface sample before it and
+/// ends at the first surface sample after it.
+pub fn split_apneas(samples: &[Sample]) -> Vec<Apnea> {
+    let mut apneas: Vec<Apnea> = Vec::new();
+    let mut i = 0;
+
+    while i < samples.len() {
+        if samples[i].depth_m < APNEA_START_M {
+            i += 1;
+            continue;
+        }
+
+        let start_s = if i > 0 { samples[i - 1].time_s } else { samples[i].time_s };
+        let mut deepest = i;
+        let mut j = i;
+        while j < samples.len() && samples[j].depth_m >= APNEA_END_M {
+            if samples[j].depth_m > samples[deepest].depth_m {
+                deepest = j;
+            }
+            j += 1;
+        }
+        let end_s = samples.get(j).unwrap_or(&samples[j - 1]).time_s;
+
+        let max_depth_m = samples[deepest].depth_m;
+        let bottom_s = samples[deepest].time_s;
+        let speed = |secs: u32| (secs > 0).then(|| max_depth_m / secs as f64);
+
+        if let Some(prev) = apneas.last_mut() {
+            prev.surface_s = Some(start_s - (prev.start_s + prev.duration_s));
+        }
+        apneas.push(Apnea {
+            number: apneas.len() as u32 + 1,
+            start_s,
+            duration_s: end_s - start_s,
+            max_depth_m,
+            descent_speed: speed(bottom_s - start_s),
+            ascent_speed: speed(end_s - bottom_s),
+            surface_s: None,
+        });
+
+        i = j;
+    }
+
+    apneas
+}
+
+/// Session summary shown for freedive dives.
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    pub apneas: usize,
+    pub total_apnea_s: u32,
+    pub deepest_m: f64,
+    pub longest_s: u32,
+    pub avg_surface_s: Option<u32>,
+    pub avg_descent_speed: Option<f64>,
+    pub avg_ascent_speed: Option<f64>,
+}
+
+/// Session totals, or `None` when no apnea went below the surface threshold.
+pub fn summarize(apneas: &[Apnea]) -> Option<SessionSummary> {
+    if apneas.is_empty() {
+        return None;
+    }
+    let surfaces: Vec<u32> = apneas.iter().filter_map(|a| a.surface_s).collect();
+    let mean = |speeds: Vec<f64>| {
+        (!speeds.is_empty()).then(|| speeds.iter().sum::<f64>() / speeds.len() as f64)
+    };
+    Some(SessionSummary {
+        apneas: apneas.len(),
+        total_apnea_s: apneas.iter().map(|a| a.duration_s).sum(),
+        deepest_m: apneas.iter().map(|a| a.max_depth_m).fold(0.0, f64::max),
+        longest_s: apneas.iter().map(|a| a.duration_s).max().unwrap_or(0),
+        avg_surface_s: (!surfaces.is_empty())
+            .then(|| surfaces.iter().sum::<u32>() / surfaces.len() as u32),
+        avg_descent_speed: mean(apneas.iter().filter_map(|a| a.descent_speed).collect()),
+        avg_ascent_speed: mean(apneas.iter().filter_map(|a| a.ascent_speed).collect()),
+    })
+}
+
+/// Export the apneas of a freedive session as CSV, one row per apnea.
+pub fn apneas_to_csv(apneas: &[Apnea]) -> String {
+    let mut csv = String::from(
+        "apnea,start_s,duration_s,max_depth_m,descent_speed_mps,ascent_speed_mps,surface_s\n",
+    );
+    for a in apneas {
+        csv.push_str(&format!(
+            "{},{},{},{:.1},{},{},{}\n",
+            a.number,
+            a.start_s,
+            a.duration_s,
+            a.max_depth_m,
+            a.descent_speed.map(|v| format!("{v:.2}")).unwrap_or_default(),
+            a.ascent_speed.map(|v| format!("{v:.2}")).unwrap_or_default(),
+            a.surface_s.map(|v| v.to_string()).unwrap_or_default(),
+        ));
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One sample per second at the given depths.
+    fn samples(depths: &[f64]) -> Vec<Sample> {
+        depths
+            .iter()
+            .enumerate()
+            .map(|(i, &depth_m)| Sample {
+                time_s: i as u32,
+                depth_m,
+                temp_c: None,
+                pressure_bar: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn splits_session_into_apneas() {
+        let session = samples(&[0.2, 2.0, 6.0, 10.0, 4.0, 0.3, 0.1, 0.2, 3.0, 8.0, 0.4]);
+        let apneas = split_apneas(&session);
+        assert_eq!(apneas.len(), 2);
+
+        let first = &apneas[0];
+        assert_eq!((first.start_s, first.duration_s, first.max_depth_m), (0, 5, 10.0));
+        assert_eq!(first.descent_speed, Some(10.0 / 3.0));
+        assert_eq!(first.ascent_speed, Some(10.0 / 2.0));
+        assert_eq!(first.surface_s, Some(2));
+
+        let second = &apneas[1];
+        assert_eq!((second.start_s, second.duration_s, second.max_depth_m), (7, 3, 8.0));
+        assert_eq!(second.surface_s, None);
+    }
+
+    #[test]
+    fn chop_around_one_metre_does_not_split_an_apnea() {
+        // Bobbing between 0.6 and 1.2 m on the way up and down
+        let session = samples(&[0.2, 1.2, 0.8, 1.1, 5.0, 12.0, 5.0, 1.1, 0.9, 1.2, 0.6, 0.2]);
+        let apneas = split_apneas(&session);
+        assert_eq!(apneas.len(), 1);
+        assert_eq!((apneas[0].start_s, apneas[0].duration_s), (0, 11));
+        assert_eq!(apneas[0].max_depth_m, 12.0);
+    }
+
+    #[test]
+    fn surface_readings_below_start_depth_are_no_apnea() {
+        let session = samples(&[0.2, 0.9, 0.7, 0.95, 0.3]);
+        assert!(split_apneas(&session).is_empty());
+    }
+}
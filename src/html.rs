@@ -0,0 +1,635 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::types::{DiveLog, PressureFill};
+use crate::xml::escape;
+
+const STYLE: &str = r##"body { font-family: system-ui, sans-serif; margin: 0; color: #222; background: #f6f7f9; }
+header { background: #1f5fbf; color: white; padding: 0.8em 2em; }
+header a { color: white; margin-right: 1.2em; text-decoration: none; }
+header a.home { font-weight: bold; font-size: 1.2em; }
+main { max-width: 1100px; margin: 0 auto; padding: 1em 2em 3em; }
+h1 { font-size: 1.6em; margin: 0.8em 0 0.4em; }
+a { color: #1f5fbf; }
+table { border-collapse: collapse; width: 100%; background: white; }
+th, td { padding: 0.35em 0.6em; text-align: left; border-bottom: 1px solid #e3e5e8; }
+th { background: #eef1f5; }
+table.sortable th { cursor: pointer; user-select: none; }
+table.sortable th.asc::after { content: " \25B2"; }
+table.sortable th.desc::after { content: " \25BC"; }
+td.num, th.num { text-align: right; }
+input.filter { padding: 0.4em; width: 20em; margin: 0.6em 0; }
+.stats { display: flex; flex-wrap: wrap; gap: 0.8em; margin: 1em 0; }
+.stat { background: white; padding: 0.6em 1em; border-radius: 6px; min-width: 8em; }
+.stat b { display: block; font-size: 1.3em; }
+dl.facts { display: grid; grid-template-columns: max-content auto max-content auto; gap: 0.3em 1.2em; background: white; padding: 1em; border-radius: 6px; }
+dl.facts dt { font-weight: bold; }
+dl.facts dd { margin: 0; }
+.chart { position: relative; background: white; border-radius: 6px; margin: 1em 0; }
+.chart canvas { width: 100%; height: 380px; display: block; }
+.tooltip { position: absolute; pointer-events: none; background: rgba(0,0,0,0.75); color: white; padding: 0.3em 0.5em; border-radius: 4px; font-size: 0.85em; display: none; white-space: pre; }
+.notes { background: white; padding: 1em; border-radius: 6px; white-space: pre-wrap; }
+nav.pager { display: flex; justify-content: space-between; margin-top: 1.5em; }
+footer { color: #888; font-size: 0.8em; text-align: center; padding: 2em; }
+"##;
+
+const SCRIPT: &str = r##"// Sortable, filterable tables
+document.querySelectorAll("table.sortable").forEach(function (table) {
+  var body = table.tBodies[0];
+  table.querySelectorAll("th").forEach(function (th, col) {
+    th.addEventListener("click", function () {
+      var asc = !th.classList.contains("asc");
+      table.querySelectorAll("th").forEach(function (h) { h.classList.remove("asc", "desc"); });
+      th.classList.add(asc ? "asc" : "desc");
+      var rows = Array.from(body.rows);
+      rows.sort(function (a, b) {
+        var x = a.cells[col].dataset.sort || a.cells[col].textContent;
+        var y = b.cells[col].dataset.sort || b.cells[col].textContent;
+        var nx = parseFloat(x), ny = parseFloat(y);
+        var cmp = (!isNaN(nx) && !isNaN(ny)) ? nx - ny : x.localeCompare(y);
+        return asc ? cmp : -cmp;
+      });
+      rows.forEach(function (r) { body.appendChild(r); });
+    });
+  });
+});
+document.querySelectorAll("input.filter").forEach(function (input) {
+  var table = document.getElementById(input.dataset.table);
+  input.addEventListener("input", function () {
+    var q = input.value.toLowerCase();
+    Array.from(table.tBodies[0].rows).forEach(function (r) {
+      r.style.display = r.textContent.toLowerCase().indexOf(q) >= 0 ? "" : "none";
+    });
+  });
+});
+
+// Depth profile chart with temperature / pressure and a hover read-out
+function drawProfile(container, dive) {
+  var canvas = container.querySelector("canvas");
+  var tip = container.querySelector(".tooltip");
+  var colors = ["#2ca02c", "#b0309b", "#c9a100", "#4aa3df"];
+  var pad = { l: 50, r: 110, t: 20, b: 35 };
+  var maxT = Math.max(1, dive.t[dive.t.length - 1] || 1);
+  var maxD = Math.max(1, Math.ceil(Math.max.apply(null, dive.depth) / 5) * 5);
+  var temps = dive.temp.filter(function (v) { return v !== null; });
+  var pressures = [].concat.apply([], dive.tanks.map(function (k) { return k.values; }))
+    .filter(function (v) { return v !== null; });
+  function range(values) {
+    var lo = Math.min.apply(null, values), hi = Math.max.apply(null, values);
+    return hi - lo < 1 ? [lo - 0.5, hi + 0.5] : [lo, hi];
+  }
+  var tRange = temps.length ? range(temps) : null;
+  var pRange = pressures.length ? range(pressures) : null;
+  var geom;
+
+  function draw() {
+    var ratio = window.devicePixelRatio || 1;
+    var w = canvas.clientWidth, h = canvas.clientHeight;
+    canvas.width = w * ratio; canvas.height = h * ratio;
+    var ctx = canvas.getContext("2d");
+    ctx.setTransform(ratio, 0, 0, ratio, 0, 0);
+    ctx.clearRect(0, 0, w, h);
+    ctx.font = "12px system-ui, sans-serif";
+    var x = function (t) { return pad.l + t / maxT * (w - pad.l - pad.r); };
+    var y = function (d) { return pad.t + d / maxD * (h - pad.t - pad.b); };
+    var scaled = function (v, r) { return h - pad.b - (v - r[0]) / (r[1] - r[0]) * (h - pad.t - pad.b); };
+    geom = { x: x, w: w };
+
+    ctx.strokeStyle = "#e3e5e8"; ctx.fillStyle = "#555";
+    var dStep = maxD > 40 ? 10 : 5;
+    for (var d = 0; d <= maxD; d += dStep) {
+      ctx.beginPath(); ctx.moveTo(pad.l, y(d)); ctx.lineTo(w - pad.r, y(d)); ctx.stroke();
+      ctx.textAlign = "right"; ctx.fillText(d + " m", pad.l - 6, y(d) + 4);
+    }
+    var tStep = [60, 120, 300, 600, 900, 1800].find(function (s) { return maxT / s <= 12; }) || 3600;
+    if (maxT < 120) tStep = 10;
+    for (var t = 0; t <= maxT; t += tStep) {
+      ctx.beginPath(); ctx.moveTo(x(t), pad.t); ctx.lineTo(x(t), h - pad.b); ctx.stroke();
+      ctx.textAlign = "center";
+      ctx.fillText(tStep < 60 ? t + " s" : Math.round(t / 60) + "'", x(t), h - pad.b + 16);
+    }
+
+    ctx.beginPath(); ctx.moveTo(x(dive.t[0]), y(0));
+    dive.t.forEach(function (t, i) { ctx.lineTo(x(t), y(dive.depth[i])); });
+    ctx.lineTo(x(dive.t[dive.t.length - 1]), y(0)); ctx.closePath();
+    ctx.fillStyle = "#dbe9fb"; ctx.fill();
+    ctx.beginPath();
+    dive.t.forEach(function (t, i) { ctx[i ? "lineTo" : "moveTo"](x(t), y(dive.depth[i])); });
+    ctx.strokeStyle = "#1f5fbf"; ctx.lineWidth = 2; ctx.stroke(); ctx.lineWidth = 1;
+
+    function series(values, r, color, dash) {
+      ctx.beginPath(); ctx.setLineDash(dash); ctx.strokeStyle = color;
+      var started = false;
+      values.forEach(function (v, i) {
+        if (v === null) return;
+        ctx[started ? "lineTo" : "moveTo"](x(dive.t[i]), scaled(v, r)); started = true;
+      });
+      ctx.stroke(); ctx.setLineDash([]);
+    }
+    ctx.textAlign = "left";
+    if (tRange) {
+      series(dive.temp, tRange, "#d62728", [6, 4]);
+      ctx.fillStyle = "#d62728";
+      ctx.fillText(tRange[1].toFixed(1) + " °C", w - pad.r + 6, scaled(tRange[1], tRange) + 4);
+      ctx.fillText(tRange[0].toFixed(1) + " °C", w - pad.r + 6, scaled(tRange[0], tRange) + 4);
+    }
+    if (pRange) {
+      dive.tanks.forEach(function (k, i) { series(k.values, pRange, colors[i % 4], []); });
+      ctx.fillStyle = "#555";
+      ctx.fillText(Math.round(pRange[1]) + " bar", w - pad.r + 56, scaled(pRange[1], pRange) + 4);
+      ctx.fillText(Math.round(pRange[0]) + " bar", w - pad.r + 56, scaled(pRange[0], pRange) + 4);
+    }
+  }
+
+  canvas.addEventListener("mousemove", function (e) {
+    var rect = canvas.getBoundingClientRect();
+    var px = e.clientX - rect.left, best = 0;
+    dive.t.forEach(function (t, i) {
+      if (Math.abs(geom.x(t) - px) < Math.abs(geom.x(dive.t[best]) - px)) best = i;
+    });
+    var t = dive.t[best];
+    var lines = [Math.floor(t / 60) + ":" + String(t % 60).padStart(2, "0"),
+                 dive.depth[best].toFixed(1) + " m"];
+    if (dive.temp[best] !== null) lines.push(dive.temp[best].toFixed(1) + " °C");
+    dive.tanks.forEach(function (k) {
+      if (k.values[best] !== null) lines.push(k.label + ": " + Math.round(k.values[best]) + " bar");
+    });
+    tip.textContent = lines.join("\n");
+    tip.style.display = "block";
+    tip.style.left = Math.min(px + 12, geom.w - 120) + "px";
+    tip.style.top = (e.clientY - rect.top + 12) + "px";
+  });
+  canvas.addEventListener("mouseleave", function () { tip.style.display = "none"; });
+  window.addEventListener("resize", draw);
+  draw();
+}
+document.querySelectorAll(".chart[data-dive]").forEach(function (el) {
+  drawProfile(el, JSON.parse(document.getElementById(el.dataset.dive).textContent));
+});
+"##;
+
+/// Lowercase ASCII file name part, e.g. "Shark & Point" -> "shark-point".
+fn slug(name: &str) -> String {
+    let mut out = String::new();
+    for c in name.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            out.push(c);
+        } else if !out.is_empty() && !out.ends_with('-') {
+            out.push('-');
+        }
+    }
+    let out = out.trim_end_matches('-').to_string();
+    if out.is_empty() {
+        "unnamed".to_string()
+    } else {
+        out
+    }
+}
+
+/// Page names for a set of labels, unique even when slugs collide.
+fn page_names<'a>(prefix: &str, labels: impl Iterator<Item = &'a str>) -> HashMap<&'a str, String> {
+    let mut names = HashMap::new();
+    let mut taken: HashMap<String, u32> = HashMap::new();
+    for label in labels {
+        if names.contains_key(label) {
+            continue;
+        }
+        let base = format!("{}-{}", prefix, slug(label));
+        let count = taken.entry(base.clone()).or_insert(0);
+        *count += 1;
+        let name = if *count == 1 {
+            format!("{}.html", base)
+        } else {
+            format!("{}-{}.html", base, count)
+        };
+        names.insert(label, name);
+    }
+    names
+}
+
+fn mmss(seconds: u32) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<link rel="stylesheet" href="style.css">
+</head>
+<body>
+<header><a class="home" href="index.html">Dive logbook</a> <a href="sites.html">Sites</a> <a href="countries.html">Countries</a> <a href="buddies.html">Buddies</a></header>
+<main>
+{body}
+</main>
+<footer>Generated by {generator}</footer>
+<script src="logbook.js"></script>
+</body>
+</html>
+"#,
+        title = escape(title),
+        body = body,
+        generator = env!("CARGO_PKG_NAME"),
+    )
+}
+
+/// Links between the generated pages.
+struct Links<'a> {
+    dives: Vec<String>,
+    sites: HashMap<&'a str, String>,
+    countries: HashMap<&'a str, String>,
+    buddies: HashMap<&'a str, String>,
+}
+
+impl Links<'_> {
+    fn link(map: &HashMap<&str, String>, label: Option<&str>) -> String {
+        match label.and_then(|l| Some((l, map.get(l)?))) {
+            Some((label, href)) => format!(r#"<a href="{}">{}</a>"#, href, escape(label)),
+            None => String::new(),
+        }
+    }
+
+    fn buddy_links(&self, dive: &DiveLog) -> String {
+        dive.buddies()
+            .into_iter()
+            .map(|b| Self::link(&self.buddies, Some(b)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Summary tiles for a set of dives.
+fn stats_html(dives: &[(usize, &DiveLog)], links: &Links) -> String {
+    if dives.is_empty() {
+        return String::new();
+    }
+    let total_s: u64 = dives.iter().map(|(_, d)| d.duration_seconds as u64).sum();
+    let (deep_i, deepest) = dives
+        .iter()
+        .max_by(|a, b| a.1.max_depth_m.total_cmp(&b.1.max_depth_m))
+        .unwrap();
+    let (long_i, longest) = dives.iter().max_by_key(|(_, d)| d.duration_seconds).unwrap();
+    let coldest = dives
+        .iter()
+        .filter_map(|(_, d)| d.temp_range().map(|(min, _)| min))
+        .min_by(f64::total_cmp);
+    let avg_depth = dives.iter().map(|(_, d)| d.max_depth_m).sum::<f64>() / dives.len() as f64;
+    let first = dives.iter().map(|(_, d)| d.start().0).min().unwrap();
+    let last = dives.iter().map(|(_, d)| d.start().0).max().unwrap();
+
+    let mut tiles = vec![
+        ("Dives".to_string(), dives.len().to_string()),
+        (
+            "Total time".to_string(),
+            format!("{}h {:02}m", total_s / 3600, total_s % 3600 / 60),
+        ),
+        (
+            "Deepest".to_string(),
+            format!(
+                r#"<a href="{}">{:.1} m</a>"#,
+                links.dives[*deep_i], deepest.max_depth_m
+            ),
+        ),
+        (
+            "Longest".to_string(),
+            format!(
+                r#"<a href="{}">{}</a>"#,
+                links.dives[*long_i],
+                mmss(longest.duration_seconds)
+            ),
+        ),
+        ("Avg max depth".to_string(), format!("{:.1} m", avg_depth)),
+    ];
+    if let Some(t) = coldest {
+        tiles.push(("Coldest".to_string(), format!("{:.1} °C", t)));
+    }
+    tiles.push((
+        "First / last".to_string(),
+        format!("{} – {}", first.format("%Y-%m-%d"), last.format("%Y-%m-%d")),
+    ));
+
+    let mut html = String::from("<div class=\"stats\">\n");
+    for (label, value) in tiles {
+        html.push_str(&format!(
+            "<div class=\"stat\">{}<b>{}</b></div>\n",
+            escape(&label),
+            value
+        ));
+    }
+    html.push_str("</div>\n");
+    html
+}
+
+/// Sortable dive table with a filter box.
+fn dive_table(id: &str, dives: &[(usize, &DiveLog)], links: &Links) -> String {
+    let mut html = format!(
+        r#"<input class="filter" data-table="{id}" placeholder="Filter dives…">
+<table class="sortable" id="{id}">
+<thead><tr><th class="num">#</th><th>Date</th><th>Site</th><th>Country</th><th class="num">Max depth</th><th class="num">Duration</th><th class="num">Temp</th><th>Mode</th><th>Buddy</th></tr></thead>
+<tbody>
+"#
+    );
+    for (i, dive) in dives {
+        let (start, _) = dive.start();
+        let temp = dive.temp_range().map(|(min, _)| min);
+        html.push_str(&format!(
+            "<tr><td class=\"num\" data-sort=\"{number}\"><a href=\"{href}\">{number}</a></td>\
+             <td data-sort=\"{sort_date}\"><a href=\"{href}\">{date}</a></td><td>{site}</td><td>{country}</td>\
+             <td class=\"num\" data-sort=\"{depth}\">{depth:.1} m</td>\
+             <td class=\"num\" data-sort=\"{duration}\">{duration_text}</td>\
+             <td class=\"num\" data-sort=\"{temp_sort}\">{temp}</td><td>{mode:?}</td><td>{buddy}</td></tr>\n",
+            number = dive.number,
+            href = links.dives[*i],
+            sort_date = start.format("%Y%m%d%H%M%S"),
+            date = start.format("%Y-%m-%d %H:%M"),
+            site = Links::link(&links.sites, dive.site.as_deref()),
+            country = Links::link(&links.countries, dive.country.as_deref()),
+            depth = dive.max_depth_m,
+            duration = dive.duration_seconds,
+            duration_text = mmss(dive.duration_seconds),
+            temp_sort = temp.map_or(String::new(), |t| t.to_string()),
+            temp = temp.map_or(String::new(), |t| format!("{:.1} °C", t)),
+            mode = dive.dive_mode,
+            buddy = links.buddy_links(dive),
+        ));
+    }
+    html.push_str("</tbody>\n</table>\n");
+    html
+}
+
+/// Chart data for a dive page, embedded as JSON.
+fn chart_data(dive: &DiveLog) -> String {
+    let tanks: Vec<serde_json::Value> = dive
+        .sample_pressures(PressureFill::Linear)
+        .into_iter()
+        .map(|(tank, values)| {
+            serde_json::json!({ "label": dive.tank_label(tank), "values": values })
+        })
+        .collect();
+    let data = serde_json::json!({
+        "t": dive.samples.iter().map(|s| s.time_s).collect::<Vec<_>>(),
+        "depth": dive.samples.iter().map(|s| s.depth_m).collect::<Vec<_>>(),
+        "temp": dive.samples.iter().map(|s| s.temp_c).collect::<Vec<_>>(),
+        "tanks": tanks,
+    });
+    // Keep the JSON from closing the <script> element
+    data.to_string().replace("</", "<\\/")
+}
+
+fn dive_page(dives: &[DiveLog], i: usize, links: &Links) -> String {
+    let dive = &dives[i];
+    let (start, _) = dive.start();
+    let time = match dive.utc_offset() {
+        Some(offset) => format!("{} (UTC{})", start.format("%H:%M:%S"), offset),
+        None => start.format("%H:%M:%S").to_string(),
+    };
+    let gas = dive
+        .gas_mixes
+        .iter()
+        .map(|g| g.name())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let pressures = dive
+        .tank_pressures()
+        .iter()
+        .map(|(tank, start, end)| format!("{}: {:.0} – {:.0} bar", dive.tank_label(*tank), start, end))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let computer = dive.computer.as_ref().map(|dc| match dc.serial {
+        Some(ref serial) => format!("{} ({})", dc.model, serial),
+        None => dc.model.clone(),
+    });
+
+    let mut facts: Vec<(&str, String)> = vec![
+        ("Date", start.format("%Y-%m-%d").to_string()),
+        ("Time", escape(&time)),
+        ("Duration", mmss(dive.duration_seconds)),
+        ("Max depth", format!("{:.1} m", dive.max_depth_m)),
+        ("Avg depth", dive.avg_depth().map_or(String::new(), |d| format!("{:.1} m", d))),
+        (
+            "Water temp",
+            dive.temp_range()
+                .map_or(String::new(), |(min, max)| format!("{:.1} – {:.1} °C", min, max)),
+        ),
+        ("Mode", format!("{:?}", dive.dive_mode)),
+        ("Gas", escape(&gas)),
+        ("Pressure", escape(&pressures)),
+        ("Computer", escape(computer.as_deref().unwrap_or(""))),
+        ("Site", Links::link(&links.sites, dive.site.as_deref())),
+        ("Country", Links::link(&links.countries, dive.country.as_deref())),
+        ("Buddy", links.buddy_links(dive)),
+        ("Source", escape(dive.source.as_deref().unwrap_or(""))),
+    ];
+    facts.extend(
+        dive.details
+            .fields()
+            .into_iter()
+            .map(|(label, value)| (label, escape(&value))),
+    );
+
+    let mut body = format!(
+        "<h1>Dive #{} <small>{}</small></h1>\n<dl class=\"facts\">\n",
+        dive.number,
+        escape(dive.site.as_deref().unwrap_or(""))
+    );
+    for (label, value) in facts.iter().filter(|(_, v)| !v.is_empty()) {
+        body.push_str(&format!("<dt>{}</dt><dd>{}</dd>\n", label, value));
+    }
+    body.push_str("</dl>\n");
+
+    if dive.samples.len() > 1 {
+        body.push_str(&format!(
+            "<div class=\"chart\" data-dive=\"profile-data\"><canvas></canvas><div class=\"tooltip\"></div></div>\n\
+             <script type=\"application/json\" id=\"profile-data\">{}</script>\n",
+            chart_data(dive)
+        ));
+    }
+    if let Some(ref notes) = dive.notes {
+        body.push_str(&format!("<h2>Notes</h2>\n<div class=\"notes\">{}</div>\n", escape(notes)));
+    }
+
+    let prev = i
+        .checked_sub(1)
+        .map(|p| format!(r#"<a href="{}">← Dive #{}</a>"#, links.dives[p], dives[p].number))
+        .unwrap_or_default();
+    let next = dives
+        .get(i + 1)
+        .map(|n| format!(r#"<a href="{}">Dive #{} →</a>"#, links.dives[i + 1], n.number))
+        .unwrap_or_default();
+    body.push_str(&format!("<nav class=\"pager\"><span>{}</span><span>{}</span></nav>\n", prev, next));
+
+    page(&format!("Dive #{}", dive.number), &body)
+}
+
+/// Overview of all values of a grouping (sites, countries or buddies).
+fn group_index(
+    title: &str,
+    groups: &BTreeMap<&str, Vec<(usize, &DiveLog)>>,
+    names: &HashMap<&str, String>,
+) -> String {
+    let mut body = format!(
+        "<h1>{title}</h1>\n<table class=\"sortable\">\n<thead><tr><th>Name</th><th class=\"num\">Dives</th>\
+         <th class=\"num\">Deepest</th><th>Last dive</th></tr></thead>\n<tbody>\n"
+    );
+    for (label, dives) in groups {
+        let deepest = dives.iter().map(|(_, d)| d.max_depth_m).fold(0.0, f64::max);
+        let last = dives.iter().map(|(_, d)| d.start().0).max().unwrap();
+        body.push_str(&format!(
+            "<tr><td><a href=\"{}\">{}</a></td><td class=\"num\">{}</td>\
+             <td class=\"num\" data-sort=\"{}\">{:.1} m</td><td>{}</td></tr>\n",
+            names[label],
+            escape(label),
+            dives.len(),
+            deepest,
+            deepest,
+            last.format("%Y-%m-%d")
+        ));
+    }
+    body.push_str("</tbody>\n</table>\n");
+    page(title, &body)
+}
+
+/// Group dives by a label, keeping each dive's index into the logbook.
+fn group<'a>(
+    dives: &'a [DiveLog],
+    labels: impl Fn(&'a DiveLog) -> Vec<&'a str>,
+) -> BTreeMap<&'a str, Vec<(usize, &'a DiveLog)>> {
+    let mut groups: BTreeMap<&str, Vec<(usize, &DiveLog)>> = BTreeMap::new();
+    for (i, dive) in dives.iter().enumerate() {
+        for label in labels(dive) {
+            groups.entry(label).or_default().push((i, dive));
+        }
+    }
+    groups
+}
+
+/// Dive tables list the newest dive first.
+fn newest_first<'a>(list: &[(usize, &'a DiveLog)]) -> Vec<(usize, &'a DiveLog)> {
+    let mut list = list.to_vec();
+    list.sort_by_key(|(_, d)| std::cmp::Reverse(d.start_utc()));
+    list
+}
+
+/// Write the logbook site into `dir`: index with statistics and the dive
+/// table, a page per dive, and pages per site, country and buddy.
+pub fn write_site(dives: &[DiveLog], dir: &Path) -> Result<usize> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    // Dive pages link to the previous and next dive in time
+    let mut dives = dives.to_vec();
+    dives.sort_by_key(|d| d.start_utc());
+    let dives = &dives[..];
+
+    let sites = group(dives, |d| d.site.as_deref().into_iter().collect());
+    let countries = group(dives, |d| d.country.as_deref().into_iter().collect());
+    let buddies = group(dives, |d| d.buddies());
+
+    let mut seen: HashMap<u32, u32> = HashMap::new();
+    let links = Links {
+        // Numbers can repeat across imported logbooks
+        dives: dives
+            .iter()
+            .map(|d| {
+                let n = seen.entry(d.number).or_insert(0);
+                *n += 1;
+                if *n == 1 {
+                    format!("dive-{}.html", d.number)
+                } else {
+                    format!("dive-{}-{}.html", d.number, n)
+                }
+            })
+            .collect(),
+        sites: page_names("site", sites.keys().copied()),
+        countries: page_names("country", countries.keys().copied()),
+        buddies: page_names("buddy", buddies.keys().copied()),
+    };
+
+    let mut files: Vec<(String, String)> = vec![
+        ("style.css".to_string(), STYLE.to_string()),
+        ("logbook.js".to_string(), SCRIPT.to_string()),
+    ];
+
+    let all = newest_first(&dives.iter().enumerate().collect::<Vec<_>>());
+    let index = format!(
+        "<h1>Dive logbook</h1>\n{}<p>{} sites in {} countries with {} buddies.</p>\n{}",
+        stats_html(&all, &links),
+        sites.len(),
+        countries.len(),
+        buddies.len(),
+        dive_table("dives", &all, &links)
+    );
+    files.push(("index.html".to_string(), page("Dive logbook", &index)));
+
+    for i in 0..dives.len() {
+        files.push((links.dives[i].clone(), dive_page(dives, i, &links)));
+    }
+
+    for (kind, groups, names) in [
+        ("Sites", &sites, &links.sites),
+        ("Countries", &countries, &links.countries),
+        ("Buddies", &buddies, &links.buddies),
+    ] {
+        files.push((
+            format!("{}.html", kind.to_lowercase()),
+            group_index(kind, groups, names),
+        ));
+        for (label, group_dives) in groups.iter() {
+            let group_dives = newest_first(group_dives);
+            let body = format!(
+                "<h1>{}</h1>\n{}{}",
+                escape(label),
+                stats_html(&group_dives, &links),
+                dive_table("dives", &group_dives, &links)
+            );
+            files.push((names[label].clone(), page(label, &body)));
+        }
+    }
+
+    for (name, contents) in &files {
+        std::fs::write(dir.join(name), contents)
+            .with_context(|| format!("Failed to write {}", dir.join(name).display()))?;
+    }
+    Ok(files.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dive(number: u32, datetime: &str) -> DiveLog {
+        DiveLog {
+            site: Some("Reef".to_string()),
+            ..DiveLog::sample_at(number, datetime)
+        }
+    }
+
+    #[test]
+    fn pages_follow_dive_time_not_input_order() {
+        let dives = [
+            dive(2, "2025-03-05 09:00"),
+            dive(3, "2025-03-06 09:00"),
+            dive(1, "2025-03-04 09:00"),
+        ];
+        let dir = std::env::temp_dir().join(format!("sirius-site-{}", std::process::id()));
+        write_site(&dives, &dir).unwrap();
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+
+        let middle = read("dive-2.html");
+        assert!(middle.contains(r#"<a href="dive-1.html">← Dive #1</a>"#));
+        assert!(middle.contains(r#"<a href="dive-3.html">Dive #3 →</a>"#));
+        assert!(middle.contains("<footer>Generated by sirius-dive</footer>"));
+
+        for page in [read("index.html"), read("site-reef.html")] {
+            let table = &page[page.find("<table").unwrap()..];
+            let pos = |n: u32| table.find(&format!("href=\"dive-{}.html\"", n)).unwrap();
+            assert!(pos(3) < pos(2) && pos(2) < pos(1));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
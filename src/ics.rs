@@ -0,0 +1,122 @@
+use chrono::NaiveDateTime;
+
+use crate::types::{DiveLog, DiveMode};
+
+/// Escape TEXT property values (RFC 5545 3.3.11).
+fn text(value: &str) -> String {
+    let mut out = String::new();
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            ';' => out.push_str("\\;"),
+            ',' => out.push_str("\\,"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Append a content line, folded at 75 octets without splitting characters.
+fn push_line(out: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+/// Stable event UID from the device serial and dive number, so importing a
+/// re-export updates the existing events.
+fn uid(dive: &DiveLog) -> String {
+    let device = dive
+        .computer
+        .as_ref()
+        .map(|dc| dc.serial.as_deref().unwrap_or(&dc.model))
+        .unwrap_or("unknown");
+    let device: String = device
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("{}-{}@{}", device, dive.number, env!("CARGO_PKG_NAME"))
+}
+
+/// DTSTART / DTEND value: UTC when the zone is known, otherwise floating
+/// local time.
+fn date_time(dive: &DiveLog, offset_s: u32) -> String {
+    let (start, _) = dive.start();
+    let shift = chrono::Duration::seconds(offset_s as i64);
+    let format = |t: NaiveDateTime| t.format("%Y%m%dT%H%M%S").to_string();
+    match dive.utc_offset() {
+        Some(_) => format!("{}Z", format(dive.start_utc() + shift)),
+        None => format(start + shift),
+    }
+}
+
+fn description(dive: &DiveLog) -> String {
+    let mut lines = vec![format!(
+        "Max depth {:.1} m, {}:{:02} min",
+        dive.max_depth_m,
+        dive.duration_seconds / 60,
+        dive.duration_seconds % 60
+    )];
+    if !matches!(dive.dive_mode, DiveMode::Gauge | DiveMode::Freedive) && !dive.gas_mixes.is_empty() {
+        let gas: Vec<String> = dive.gas_mixes.iter().map(|g| g.name()).collect();
+        lines.push(format!("Gas: {}", gas.join(", ")));
+    } else {
+        lines.push(format!("Mode: {:?}", dive.dive_mode));
+    }
+    let buddies = dive.buddies();
+    if !buddies.is_empty() {
+        lines.push(format!("Buddy: {}", buddies.join(", ")));
+    }
+    if let Some(ref notes) = dive.notes {
+        lines.push(String::new());
+        lines.push(notes.clone());
+    }
+    lines.join("\n")
+}
+
+/// Build an iCalendar file with one VEVENT per dive.
+pub fn dives_to_ics(dives: &[DiveLog]) -> String {
+    let mut out = String::new();
+    let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+    push_line(&mut out, "BEGIN:VCALENDAR");
+    push_line(&mut out, "VERSION:2.0");
+    push_line(
+        &mut out,
+        &format!("PRODID:-//{}//{}//EN", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+    );
+    push_line(&mut out, "CALSCALE:GREGORIAN");
+    for dive in dives {
+        let summary = match dive.site {
+            Some(ref site) => format!("Dive #{} – {}", dive.number, site),
+            None => format!("Dive #{}", dive.number),
+        };
+        let location: Vec<&str> = [dive.site.as_deref(), dive.country.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect();
+
+        push_line(&mut out, "BEGIN:VEVENT");
+        push_line(&mut out, &format!("UID:{}", uid(dive)));
+        push_line(&mut out, &format!("DTSTAMP:{}", stamp));
+        push_line(&mut out, &format!("DTSTART:{}", date_time(dive, 0)));
+        push_line(&mut out, &format!("DTEND:{}", date_time(dive, dive.duration_seconds)));
+        push_line(&mut out, &format!("SUMMARY:{}", text(&summary)));
+        if !location.is_empty() {
+            push_line(&mut out, &format!("LOCATION:{}", text(&location.join(", "))));
+        }
+        push_line(&mut out, &format!("DESCRIPTION:{}", text(&description(dive))));
+        push_line(&mut out, "END:VEVENT");
+    }
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
@@ -1,23 +1,44 @@
 mod ble;
+mod columnar;
+mod dl7;
+mod fit;
+mod freedive;
+mod html;
+mod ics;
+mod map;
 mod parser;
+mod pdf;
+mod plot;
 mod protocol;
+mod ssi;
+mod store;
+mod subsurface;
+mod summary;
 mod tui;
 mod types;
+mod uddf;
+mod xml;
 
-use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use anyhow::{Context, Result};
 use btleplug::api::Peripheral as _;
 use clap::{Parser, Subcommand, ValueEnum};
 
+use crate::store::Store;
+use crate::summary::SummaryColumn;
 use crate::types::*;
 
 #[derive(Parser)]
 #[command(name = "sirius-dive")]
 #[command(about = "Extract dive logs from Mares Sirius dive computer via BLE")]
 struct Cli {
+    /// SQLite logbook to use instead of the JSON file (see `migrate`)
+    #[arg(long, global = true)]
+    db: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -59,6 +80,15 @@ enum Commands {
         /// Save raw dive data for debugging
         #[arg(long)]
         save_raw: Option<PathBuf>,
+
+        /// Time zone of the dive computer clock for newly downloaded dives
+        /// (e.g. "+02:00" or "Europe/Paris")
+        #[arg(long, allow_hyphen_values = true)]
+        tz: Option<DiveTz>,
+
+        /// How tank pressure readings fill sample rows in CSV output
+        #[arg(long, default_value = "linear")]
+        pressure_fill: PressureFill,
     },
 
     /// Raw protocol debug: test ECOP SDO communication
@@ -84,6 +114,59 @@ enum Commands {
         /// Path to dives.json to enrich
         #[arg(short, long, default_value = "dives.json")]
         json: PathBuf,
+
+        /// Language of the SSI export (default: detected from the header row)
+        #[arg(long, conflicts_with = "mapping")]
+        lang: Option<ssi::SsiLanguage>,
+
+        /// TOML file naming the columns of the export, for other languages or
+        /// to read depth, dive time, notes and details
+        #[arg(long)]
+        mapping: Option<PathBuf>,
+
+        /// Largest difference in minutes between SSI and dive start times
+        #[arg(long, default_value = "5")]
+        tolerance: i64,
+
+        /// Added to SSI times before matching, e.g. "-01:00" when the SSI log
+        /// is one hour ahead of the dive computer clock
+        #[arg(long, default_value = "0", value_parser = parse_shift, allow_hyphen_values = true)]
+        shift: i64,
+
+        /// What to do with values the logbook already has
+        #[arg(long, value_enum, default_value = "overwrite")]
+        policy: MergePolicy,
+
+        /// Ask before replacing or keeping each conflicting value
+        #[arg(short, long, conflicts_with = "dry_run")]
+        interactive: bool,
+
+        /// Show the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Set the time zone of dives, e.g. for all dives of a trip
+    SetTz {
+        /// Time zone of the dive computer clock (e.g. "+02:00" or "Europe/Paris")
+        #[arg(long, allow_hyphen_values = true, required_unless_present = "clear")]
+        tz: Option<DiveTz>,
+
+        /// Remove the time zone instead of setting one
+        #[arg(long, conflicts_with = "tz")]
+        clear: bool,
+
+        /// First dive date to update (YYYY-MM-DD, inclusive). Defaults to the first dive.
+        #[arg(long)]
+        from: Option<chrono::NaiveDate>,
+
+        /// Last dive date to update (YYYY-MM-DD, inclusive). Defaults to the last dive.
+        #[arg(long)]
+        to: Option<chrono::NaiveDate>,
+
+        /// Path to dives.json to update
+        #[arg(short, long, default_value = "dives.json")]
+        json: PathBuf,
     },
 
     /// Overlay dive data (depth, temp, pressure) onto a video using ffmpeg
@@ -96,20 +179,26 @@ enum Commands {
         #[arg(short, long, default_value = "dives.json")]
         json: PathBuf,
 
-        /// Time offset in seconds added to dive log start time to correct for
-        /// missing second precision. Dive logs are truncated to the minute, so the
-        /// recorded start is typically 0–59s early. A positive offset shifts the
-        /// dive time forward (common case); a negative offset shifts it back (rare).
+        /// Time offset in seconds added to dive log start time. Dives with a
+        /// seconds-precision start time recovered from the profile rarely need
+        /// one. Otherwise dive logs are truncated to the minute, so the recorded
+        /// start is typically 0–59s early. A positive offset shifts the dive
+        /// time forward (common case); a negative offset shifts it back (rare).
         #[arg(short, long, default_value = "0", allow_hyphen_values = true)]
         offset: i64,
     },
 
     /// Parse previously downloaded raw dive data (offline, no BLE needed)
     Parse {
-        /// Directory containing raw dive data (dive_NNN_header.bin / dive_NNN_profile.bin)
+        /// Directory containing raw dive data (dive_NNN_header.bin / dive_NNN_profile.bin,
+        /// or whole dives as dive_NNN.bin for pre-GENIUS models)
         #[arg(short, long)]
         raw_dir: PathBuf,
 
+        /// Dive computer model the data was downloaded from (e.g. "Sirius", "Icon HD")
+        #[arg(short, long, default_value = "Sirius")]
+        model: Model,
+
         /// Output file path
         #[arg(short, long, default_value = "dives.json")]
         output: PathBuf,
@@ -117,6 +206,148 @@ enum Commands {
         /// Output format
         #[arg(short, long, default_value = "json")]
         format: OutputFormat,
+
+        /// Time zone of the dive computer clock (e.g. "+02:00" or "Europe/Paris")
+        #[arg(long, allow_hyphen_values = true)]
+        tz: Option<DiveTz>,
+
+        /// How tank pressure readings fill sample rows in CSV output
+        #[arg(long, default_value = "linear")]
+        pressure_fill: PressureFill,
+    },
+
+    /// Export dives from dives.json to another format (offline)
+    Export {
+        /// Path to dives.json
+        #[arg(short, long, default_value = "dives.json")]
+        json: PathBuf,
+
+        /// Output file path (CSV: stem for the per-dive files)
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Output format
+        #[arg(short, long)]
+        format: OutputFormat,
+
+        /// How tank pressure readings fill sample rows in CSV output
+        #[arg(long, default_value = "linear")]
+        pressure_fill: PressureFill,
+
+        /// Leave out site, country, buddy names, notes and the dive computer
+        /// serial, e.g. for DL7 files sent to DAN
+        #[arg(long)]
+        anonymize: bool,
+
+        /// Columns of the summary CSV, comma separated (default: all)
+        #[arg(long, value_delimiter = ',')]
+        columns: Vec<SummaryColumn>,
+
+        /// Field delimiter of the summary CSV (a character or "tab")
+        #[arg(long, default_value = ",", value_parser = parse_delimiter)]
+        delimiter: char,
+    },
+
+    /// Import dives from a UDDF or Subsurface XML logbook into dives.json
+    Import {
+        /// UDDF (.uddf) or Subsurface (.ssrf / .xml) logbook to import
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Path to dives.json to merge into (created if missing)
+        #[arg(short, long, default_value = "dives.json")]
+        json: PathBuf,
+    },
+
+    /// Render dives as printable logbook pages (one page per dive) into a PDF
+    LogbookPdf {
+        /// Path to dives.json
+        #[arg(short, long, default_value = "dives.json")]
+        json: PathBuf,
+
+        /// Output PDF file
+        #[arg(short, long, default_value = "logbook.pdf")]
+        output: PathBuf,
+
+        /// Dive numbers to include, comma separated (default: all)
+        #[arg(long, value_delimiter = ',')]
+        dive: Vec<u32>,
+
+        /// First dive date to include (YYYY-MM-DD, inclusive)
+        #[arg(long)]
+        from: Option<chrono::NaiveDate>,
+
+        /// Last dive date to include (YYYY-MM-DD, inclusive)
+        #[arg(long)]
+        to: Option<chrono::NaiveDate>,
+    },
+
+    /// Render a dive's depth profile to SVG or PNG (by output extension)
+    Plot {
+        /// Path to dives.json
+        #[arg(short, long, default_value = "dives.json")]
+        json: PathBuf,
+
+        /// Output file (.svg or .png)
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Dive number to plot (default: the most recent dive)
+        #[arg(long)]
+        dive: Option<u32>,
+
+        /// Show water temperature on a second axis
+        #[arg(long)]
+        temp: bool,
+
+        /// Show tank pressure on a second axis
+        #[arg(long)]
+        pressure: bool,
+
+        /// Image width in SVG units
+        #[arg(long, default_value = "1200")]
+        width: u32,
+
+        /// Image height in SVG units
+        #[arg(long, default_value = "600")]
+        height: u32,
+
+        /// Pixels per SVG unit in PNG output
+        #[arg(long, default_value = "2")]
+        scale: f32,
+    },
+
+    /// Generate a static HTML site (index, dive, site, country and buddy pages)
+    Site {
+        /// Path to dives.json
+        #[arg(short, long, default_value = "dives.json")]
+        json: PathBuf,
+
+        /// Output directory
+        #[arg(short, long, default_value = "site")]
+        output: PathBuf,
+    },
+
+    /// Write dive sites as GPX waypoints or KML placemarks (by output extension)
+    Map {
+        /// Path to dives.json
+        #[arg(short, long, default_value = "dives.json")]
+        json: PathBuf,
+
+        /// Site coordinate table (.csv with site,lat,lon[,country] or .toml)
+        #[arg(short, long)]
+        coords: Option<PathBuf>,
+
+        /// Output file (.gpx or .kml)
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+
+    /// Copy the dives of a JSON logbook into the SQLite database given by --db
+    Migrate {
+        /// Path to dives.json to copy from
+        #[arg(short, long, default_value = "dives.json")]
+        json: PathBuf,
     },
 }
 
@@ -124,11 +355,39 @@ enum Commands {
 enum OutputFormat {
     Json,
     Csv,
+    /// UDDF 3.2 document
+    Uddf,
+    /// Subsurface XML logbook (.ssrf)
+    Subsurface,
+    /// DAN DL7 file
+    Dl7,
+    /// Parquet tables (`{stem}_dives.parquet`, `{stem}_samples.parquet`)
+    Parquet,
+    /// Arrow IPC tables (`{stem}_dives.arrow`, `{stem}_samples.arrow`)
+    Arrow,
+    /// One row per dive (logbook summary)
+    SummaryCsv,
+    /// Garmin FIT activity per dive (`{stem}_NNN.fit`)
+    Fit,
+    /// iCalendar file with one event per dive
+    Ics,
+}
+
+/// How correlate treats values already in the logbook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum MergePolicy {
+    /// Replace existing values with the SSI ones
+    Overwrite,
+    /// Leave dives that already have any correlated value untouched
+    Keep,
+    /// Only fill in values that are still empty
+    FillEmpty,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    let db = cli.db;
 
     match cli.command {
         Commands::Scan { timeout, enumerate } => cmd_scan(timeout, enumerate).await,
@@ -138,20 +397,109 @@ async fn main() -> Result<()> {
             output,
             format,
             save_raw,
-        } => cmd_download(address, output, format, save_raw).await,
+            tz,
+            pressure_fill,
+        } => cmd_download(db, address, output, format, save_raw, tz, pressure_fill).await,
         Commands::Debug { address } => cmd_debug(address).await,
-        Commands::View { input } => tui::run(input),
-        Commands::Correlate { csv, json } => cmd_correlate(csv, json),
+        Commands::View { input } => tui::run(Store::new(db, input)),
+        Commands::Correlate {
+            csv,
+            json,
+            lang,
+            mapping,
+            tolerance,
+            shift,
+            policy,
+            interactive,
+            dry_run,
+        } => {
+            let mapping = match (lang, mapping) {
+                (Some(lang), _) => Some(lang.mapping()),
+                (None, Some(path)) => Some(ssi::SsiMapping::load(&path)?),
+                (None, None) => None,
+            };
+            let options = CorrelateOptions {
+                tolerance,
+                shift,
+                policy,
+                interactive,
+                dry_run,
+            };
+            cmd_correlate(csv, Store::new(db, json), mapping, &options)
+        }
+        Commands::SetTz {
+            tz,
+            clear: _,
+            from,
+            to,
+            json,
+        } => cmd_set_tz(tz, from, to, Store::new(db, json)),
         Commands::Watermark {
             video,
             json,
             offset,
-        } => cmd_watermark(video, json, offset),
+        } => cmd_watermark(video, Store::new(db, json), offset),
         Commands::Parse {
             raw_dir,
+            model,
+            output,
+            format,
+            tz,
+            pressure_fill,
+        } => cmd_parse(db, raw_dir, model, output, format, tz, pressure_fill),
+        Commands::Export {
+            json,
+            output,
+            format,
+            pressure_fill,
+            anonymize,
+            columns,
+            delimiter,
+        } => cmd_export(
+            Store::new(db, json),
             output,
             format,
-        } => cmd_parse(raw_dir, output, format),
+            pressure_fill,
+            anonymize,
+            &columns,
+            delimiter,
+        ),
+        Commands::Import { input, json } => cmd_import(input, Store::new(db, json)),
+        Commands::LogbookPdf {
+            json,
+            output,
+            dive,
+            from,
+            to,
+        } => cmd_logbook_pdf(Store::new(db, json), output, &dive, from, to),
+        Commands::Plot {
+            json,
+            output,
+            dive,
+            temp,
+            pressure,
+            width,
+            height,
+            scale,
+        } => cmd_plot(
+            Store::new(db, json),
+            output,
+            dive,
+            plot::PlotOptions {
+                width,
+                height,
+                temp,
+                pressure,
+            },
+            scale,
+        ),
+        Commands::Site { json, output } => cmd_site(Store::new(db, json), output),
+        Commands::Map {
+            json,
+            coords,
+            output,
+        } => cmd_map(Store::new(db, json), coords, output),
+        Commands::Migrate { json } => cmd_migrate(json, db),
     }
 }
 
@@ -360,35 +708,38 @@ async fn cmd_info(address: Option<String>) -> Result<()> {
 // ── Download ──
 
 async fn cmd_download(
+    db: Option<PathBuf>,
     address: Option<String>,
     output: PathBuf,
     format: OutputFormat,
     save_raw: Option<PathBuf>,
+    tz: Option<DiveTz>,
+    pressure_fill: PressureFill,
 ) -> Result<()> {
-    // Load existing dives from output file (if any) for incremental download
+    // JSON output goes to the logbook (dives.json or --db), which is
+    // extended incrementally
+    let store = matches!(format, OutputFormat::Json).then(|| Store::new(db, output.clone()));
+
+    // Load existing dives (if any) for incremental download
     let mut existing_dives: Vec<DiveLog> = Vec::new();
     let mut existing_numbers: HashSet<u32> = HashSet::new();
 
-    if matches!(format, OutputFormat::Json) && output.exists() {
-        match std::fs::read_to_string(&output) {
-            Ok(contents) => match serde_json::from_str::<DiveData>(&contents) {
-                Ok(data) => {
-                    for dive in &data.dives {
-                        existing_numbers.insert(dive.number);
-                    }
-                    eprintln!(
-                        "Loaded {} existing dive(s) from {}",
-                        data.dives.len(),
-                        output.display()
-                    );
-                    existing_dives = data.dives;
-                }
-                Err(e) => {
-                    eprintln!("Warning: could not parse {}: {e}", output.display());
+    if let Some(store) = store.as_ref().filter(|s| s.exists()) {
+        match store.load() {
+            Ok(data) => {
+                // Imported dives have numbers from other logbooks
+                for dive in data.dives.iter().filter(|d| d.source.is_none()) {
+                    existing_numbers.insert(dive.number);
                 }
-            },
+                eprintln!(
+                    "Loaded {} existing dive(s) from {}",
+                    data.dives.len(),
+                    store.path().display()
+                );
+                existing_dives = data.dives;
+            }
             Err(e) => {
-                eprintln!("Warning: could not read {}: {e}", output.display());
+                eprintln!("Warning: {e:#}");
             }
         }
     }
@@ -400,6 +751,18 @@ async fn cmd_download(
     let info = protocol::get_device_info(&mut conn).await?;
     eprintln!("Connected to {}", info.model_name);
 
+    let serial = match protocol::read_pcb_number(&mut conn).await {
+        Ok(pcb) => Some(pcb),
+        Err(e) => {
+            eprintln!("Warning: could not read PCB number: {e}");
+            None
+        }
+    };
+    let computer = DiveComputer {
+        model: info.model_name.clone(),
+        serial,
+    };
+
     // Set datetime
     if let Err(e) = protocol::set_datetime(&mut conn).await {
         eprintln!("Warning: could not set datetime: {e}");
@@ -441,8 +804,11 @@ async fn cmd_download(
             std::fs::write(raw_dir.join(format!("dive_{i:03}_profile.bin")), &profile)?;
         }
 
+        // The ECOP objects have the GENIUS layout whatever the model
         match parser::parse_dive_ecop(i as u32, &header, &profile) {
-            Ok(dive) => {
+            Ok(mut dive) => {
+                dive.timezone = tz;
+                dive.computer = Some(computer.clone());
                 eprintln!(
                     "\r  Dive #{}: {} | {:.1}m | {}s | {} samples",
                     dive.number,
@@ -469,48 +835,129 @@ async fn cmd_download(
         eprintln!("Downloaded {} new dive(s)", new_dives.len());
     }
 
-    // Merge existing + new dives
-    let mut all_dives = existing_dives;
-    all_dives.append(&mut new_dives);
-    all_dives.sort_by_key(|d| d.number);
+    if let Some(store) = store {
+        if !new_dives.is_empty() {
+            store.save_dives(&new_dives)?;
+        }
+        eprintln!(
+            "Dive data saved to {} ({} dives)",
+            store.path().display(),
+            existing_dives.len() + new_dives.len()
+        );
+        return Ok(());
+    }
 
-    if all_dives.is_empty() {
+    if new_dives.is_empty() {
         eprintln!("No dives could be parsed.");
         return Ok(());
     }
 
-    // Export
+    write_output(new_dives, &output, &format, pressure_fill)
+}
+
+/// Write dives to `output` in the requested format.
+fn write_output(
+    dives: Vec<DiveLog>,
+    output: &Path,
+    format: &OutputFormat,
+    pressure_fill: PressureFill,
+) -> Result<()> {
     match format {
         OutputFormat::Json => {
-            let data = DiveData { dives: all_dives };
+            let data = DiveData { dives };
             let json = serde_json::to_string_pretty(&data)?;
-            std::fs::write(&output, &json)?;
+            std::fs::write(output, &json)?;
             eprintln!("Dive data saved to {} ({} dives)", output.display(), data.dives.len());
         }
-        OutputFormat::Csv => {
-            for dive in &all_dives {
-                let stem = output
-                    .file_stem()
-                    .unwrap_or_default()
-                    .to_string_lossy();
-                let dir = output.parent().unwrap_or(std::path::Path::new("."));
-                let csv_path = dir.join(format!("{}_{:03}.csv", stem, dive.number));
-                let csv = parser::dive_to_csv(dive);
-                std::fs::write(&csv_path, &csv)?;
-                eprintln!("  Dive #{} -> {}", dive.number, csv_path.display());
+        OutputFormat::Csv => write_csv_files(&dives, output, pressure_fill)?,
+        OutputFormat::Uddf => {
+            std::fs::write(output, uddf::dives_to_uddf(&dives))?;
+            eprintln!("UDDF saved to {} ({} dives)", output.display(), dives.len());
+        }
+        OutputFormat::Subsurface => {
+            std::fs::write(output, subsurface::dives_to_ssrf(&dives))?;
+            eprintln!("Subsurface logbook saved to {} ({} dives)", output.display(), dives.len());
+        }
+        OutputFormat::Dl7 => {
+            std::fs::write(output, dl7::dives_to_dl7(&dives))?;
+            eprintln!("DL7 saved to {} ({} dives)", output.display(), dives.len());
+        }
+        OutputFormat::Parquet | OutputFormat::Arrow => {
+            let table_format = match format {
+                OutputFormat::Parquet => columnar::TableFormat::Parquet,
+                _ => columnar::TableFormat::ArrowIpc,
+            };
+            let paths = columnar::write_tables(&dives, output, pressure_fill, table_format)?;
+            for path in paths {
+                eprintln!("  {}", path.display());
+            }
+            eprintln!("{} dives saved", dives.len());
+        }
+        OutputFormat::SummaryCsv => {
+            let csv = summary::dives_to_summary_csv(&dives, &SummaryColumn::ALL, ',');
+            std::fs::write(output, csv)?;
+            eprintln!("Summary saved to {} ({} dives)", output.display(), dives.len());
+        }
+        OutputFormat::Fit => {
+            let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+            let dir = output.parent().unwrap_or(Path::new("."));
+            for dive in &dives {
+                let fit_path = dir.join(format!("{}_{:03}.fit", stem, dive.number));
+                std::fs::write(&fit_path, fit::dive_to_fit(dive))?;
+                eprintln!("  Dive #{} -> {}", dive.number, fit_path.display());
             }
         }
+        OutputFormat::Ics => {
+            std::fs::write(output, ics::dives_to_ics(&dives))?;
+            eprintln!("Calendar saved to {} ({} dives)", output.display(), dives.len());
+        }
     }
+    Ok(())
+}
 
+/// Write one `{stem}_NNN.csv` profile per dive next to `output`, plus a
+/// `{stem}_NNN_apneas.csv` for freedive sessions.
+fn write_csv_files(dives: &[DiveLog], output: &Path, pressure_fill: PressureFill) -> Result<()> {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let dir = output.parent().unwrap_or(Path::new("."));
+    for dive in dives {
+        let csv_path = dir.join(format!("{}_{:03}.csv", stem, dive.number));
+        let csv = parser::dive_to_csv(dive, pressure_fill);
+        std::fs::write(&csv_path, &csv)?;
+        eprintln!("  Dive #{} -> {}", dive.number, csv_path.display());
+
+        if !dive.apneas.is_empty() {
+            let apnea_path = dir.join(format!("{}_{:03}_apneas.csv", stem, dive.number));
+            std::fs::write(&apnea_path, freedive::apneas_to_csv(&dive.apneas))?;
+            eprintln!("    {} apneas -> {}", dive.apneas.len(), apnea_path.display());
+        }
+    }
     Ok(())
 }
 
 // ── Parse (offline) ──
 
-fn cmd_parse(raw_dir: PathBuf, output: PathBuf, format: OutputFormat) -> Result<()> {
-    // Count available dives
+fn cmd_parse(
+    db: Option<PathBuf>,
+    raw_dir: PathBuf,
+    model: Model,
+    output: PathBuf,
+    format: OutputFormat,
+    tz: Option<DiveTz>,
+    pressure_fill: PressureFill,
+) -> Result<()> {
+    // Count available dives: header/profile pairs, or whole pre-GENIUS dives
+    let whole_dives = !model.is_genius() && raw_dir.join("dive_000.bin").exists();
     let mut dive_count = 0u16;
-    while raw_dir.join(format!("dive_{:03}_header.bin", dive_count)).exists() {
+    loop {
+        let name = if whole_dives {
+            format!("dive_{:03}.bin", dive_count)
+        } else {
+            format!("dive_{:03}_header.bin", dive_count)
+        };
+        if !raw_dir.join(name).exists() {
+            break;
+        }
         dive_count += 1;
     }
 
@@ -518,15 +965,38 @@ fn cmd_parse(raw_dir: PathBuf, output: PathBuf, format: OutputFormat) -> Result<
         anyhow::bail!("No dive files found in {}", raw_dir.display());
     }
 
-    eprintln!("Found {} raw dive file(s) in {}", dive_count, raw_dir.display());
+    eprintln!(
+        "Found {} raw {} dive file(s) in {}",
+        dive_count,
+        model.name(),
+        raw_dir.display()
+    );
 
     let mut dives = Vec::new();
     for i in 0..dive_count {
-        let header = std::fs::read(raw_dir.join(format!("dive_{i:03}_header.bin")))?;
-        let profile = std::fs::read(raw_dir.join(format!("dive_{i:03}_profile.bin")))?;
+        let (header, profile) = if whole_dives {
+            let data = std::fs::read(raw_dir.join(format!("dive_{i:03}.bin")))?;
+            match parser::split_iconhd_dive(model, &data) {
+                Ok(parts) => parts,
+                Err(e) => {
+                    eprintln!("  Dive {i}: parse error: {e}");
+                    continue;
+                }
+            }
+        } else {
+            (
+                std::fs::read(raw_dir.join(format!("dive_{i:03}_header.bin")))?,
+                std::fs::read(raw_dir.join(format!("dive_{i:03}_profile.bin")))?,
+            )
+        };
 
-        match parser::parse_dive_ecop(i as u32, &header, &profile) {
-            Ok(dive) => {
+        match parser::parse_dive(model, i as u32, &header, &profile) {
+            Ok(mut dive) => {
+                dive.timezone = tz;
+                dive.computer = Some(DiveComputer {
+                    model: model.name().to_string(),
+                    serial: None,
+                });
                 eprintln!(
                     "  Dive #{}: {} | {:.1}m | {}min | {} samples | {:?}",
                     dive.number,
@@ -551,206 +1021,551 @@ fn cmd_parse(raw_dir: PathBuf, output: PathBuf, format: OutputFormat) -> Result<
 
     eprintln!("Parsed {} dive(s)", dives.len());
 
-    match format {
-        OutputFormat::Json => {
-            let data = DiveData { dives };
-            let json = serde_json::to_string_pretty(&data)?;
-            std::fs::write(&output, &json)?;
-            eprintln!("Dive data saved to {}", output.display());
-        }
-        OutputFormat::Csv => {
-            for dive in &dives {
-                let stem = output
-                    .file_stem()
-                    .unwrap_or_default()
-                    .to_string_lossy();
-                let dir = output.parent().unwrap_or(std::path::Path::new("."));
-                let csv_path = dir.join(format!("{}_{:03}.csv", stem, dive.number));
-                let csv = parser::dive_to_csv(dive);
-                std::fs::write(&csv_path, &csv)?;
-                eprintln!("  Dive #{} -> {}", dive.number, csv_path.display());
-            }
-        }
+    if let (OutputFormat::Json, Some(db)) = (&format, db) {
+        let store = Store::Sqlite(db);
+        store.save_dives(&dives)?;
+        eprintln!("Dive data saved to {}", store.path().display());
+        return Ok(());
     }
 
-    Ok(())
+    write_output(dives, &output, &format, pressure_fill)
 }
 
-// ── Correlate ──
+// ── Export (offline) ──
+
+fn cmd_export(
+    store: Store,
+    output: PathBuf,
+    format: OutputFormat,
+    pressure_fill: PressureFill,
+    anonymize: bool,
+    columns: &[SummaryColumn],
+    delimiter: char,
+) -> Result<()> {
+    let mut data = store.load()?;
+
+    if data.dives.is_empty() {
+        anyhow::bail!("No dives found in {}", store.path().display());
+    }
+
+    if anonymize {
+        data.dives.iter_mut().for_each(DiveLog::anonymize);
+    }
 
-struct SsiRecord {
-    datetime: chrono::NaiveDateTime,
-    site: String,
-    country: String,
-    buddy: String,
+    if let OutputFormat::SummaryCsv = format {
+        let columns = if columns.is_empty() { &SummaryColumn::ALL[..] } else { columns };
+        std::fs::write(&output, summary::dives_to_summary_csv(&data.dives, columns, delimiter))?;
+        eprintln!("Summary saved to {} ({} dives)", output.display(), data.dives.len());
+        return Ok(());
+    }
+
+    write_output(data.dives, &output, &format, pressure_fill)
 }
 
-/// Parse a CSV line handling quoted fields with escaped quotes.
-fn parse_csv_line(line: &str) -> Vec<String> {
-    let mut fields = Vec::new();
-    let mut current = String::new();
-    let mut in_quotes = false;
-    let mut chars = line.chars().peekable();
-
-    while let Some(c) = chars.next() {
-        if in_quotes {
-            if c == '"' {
-                if chars.peek() == Some(&'"') {
-                    chars.next();
-                    current.push('"');
-                } else {
-                    in_quotes = false;
-                }
-            } else {
-                current.push(c);
+/// `--delimiter` value: a single character, or "tab".
+fn parse_delimiter(s: &str) -> Result<char, String> {
+    match s {
+        "tab" | "\\t" => Ok('\t'),
+        _ => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c),
+                _ => Err(format!("expected a single character or \"tab\", got {:?}", s)),
             }
-        } else if c == '"' {
-            in_quotes = true;
-        } else if c == ',' {
-            fields.push(current.clone());
-            current.clear();
-        } else {
-            current.push(c);
         }
     }
-    fields.push(current);
-    fields
 }
 
-/// Clean buddy field: normalize whitespace, strip trailing "Sirius"/"Mares", trim.
-fn clean_buddy(raw: &str) -> String {
-    let normalized: String = raw.split_whitespace().collect::<Vec<_>>().join(" ");
-    let trimmed = normalized
-        .trim_end_matches("Sirius")
-        .trim_end_matches("Mares")
-        .trim();
-    trimmed.to_string()
+// ── Logbook PDF ──
+
+fn cmd_logbook_pdf(
+    store: Store,
+    output: PathBuf,
+    numbers: &[u32],
+    from: Option<chrono::NaiveDate>,
+    to: Option<chrono::NaiveDate>,
+) -> Result<()> {
+    let data = store.load()?;
+
+    let dives: Vec<DiveLog> = data
+        .dives
+        .into_iter()
+        .filter(|d| numbers.is_empty() || numbers.contains(&d.number))
+        .filter(|d| {
+            let date = d.datetime.date();
+            !(from.is_some_and(|f| date < f) || to.is_some_and(|t| date > t))
+        })
+        .collect();
+    if dives.is_empty() {
+        anyhow::bail!("No matching dives in {}", store.path().display());
+    }
+
+    std::fs::write(&output, pdf::dives_to_pdf(&dives))?;
+    eprintln!("Logbook saved to {} ({} pages)", output.display(), dives.len());
+    Ok(())
+}
+
+// ── Plot ──
+
+fn cmd_plot(
+    store: Store,
+    output: PathBuf,
+    number: Option<u32>,
+    options: plot::PlotOptions,
+    scale: f32,
+) -> Result<()> {
+    let data = store.load()?;
+
+    let dive = data
+        .dives
+        .iter()
+        .filter(|d| number.is_none_or(|n| d.number == n))
+        .max_by_key(|d| d.start_utc())
+        .with_context(|| match number {
+            Some(n) => format!("Dive #{} not found in {}", n, store.path().display()),
+            None => format!("No dives found in {}", store.path().display()),
+        })?;
+
+    let svg = plot::dive_to_svg(dive, &options);
+    let extension = output
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("svg") => std::fs::write(&output, svg)?,
+        Some("png") => std::fs::write(&output, plot::svg_to_png(&svg, scale)?)?,
+        _ => anyhow::bail!("Unknown plot format {} (use .svg or .png)", output.display()),
+    }
+    eprintln!("Dive #{} plotted to {}", dive.number, output.display());
+    Ok(())
+}
+
+// ── Site ──
+
+fn cmd_site(store: Store, output: PathBuf) -> Result<()> {
+    let dives = store.load()?.dives;
+    if dives.is_empty() {
+        anyhow::bail!("No dives found in {}", store.path().display());
+    }
+
+    let files = html::write_site(&dives, &output)?;
+    eprintln!(
+        "Site with {} dives written to {} ({} files)",
+        dives.len(),
+        output.display(),
+        files
+    );
+    Ok(())
 }
 
-/// Parse SSI CSV export into records.
-fn parse_ssi_csv(contents: &str) -> Vec<SsiRecord> {
-    let mut lines = contents.lines();
+// ── Map ──
+
+fn cmd_map(store: Store, coords: Option<PathBuf>, output: PathBuf) -> Result<()> {
+    let data = store.load()?;
+    let table = match coords {
+        Some(ref path) => map::CoordTable::load(path)?,
+        None => map::CoordTable::default(),
+    };
+    let sites = map::summarize(&data.dives, &table);
+    if sites.is_empty() {
+        anyhow::bail!("No dives with a site in {}", store.path().display());
+    }
 
-    // Parse header to find column indices
-    let header_line = match lines.next() {
-        Some(h) => h,
-        None => return Vec::new(),
+    let extension = output
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    let contents = match extension.as_deref() {
+        Some("gpx") => map::sites_to_gpx(&sites),
+        Some("kml") => map::sites_to_kml(&sites),
+        _ => anyhow::bail!("Unknown map format {} (use .gpx or .kml)", output.display()),
     };
-    let headers = parse_csv_line(header_line);
+    std::fs::write(&output, contents)?;
+
+    let located = sites.iter().filter(|s| s.coords.is_some()).count();
+    eprintln!("{} of {} sites saved to {}", located, sites.len(), output.display());
+    let missing: Vec<_> = sites.iter().filter(|s| s.coords.is_none()).collect();
+    if !missing.is_empty() {
+        eprintln!("\nSites without coordinates (add them to the coordinate table):");
+        for site in missing {
+            eprintln!(
+                "  \"{}\",,,{}    ({} dive{})",
+                site.name.replace('"', "\"\""),
+                site.country.unwrap_or(""),
+                site.dives.len(),
+                if site.dives.len() == 1 { "" } else { "s" }
+            );
+        }
+    }
+    Ok(())
+}
 
-    let col = |name: &str| headers.iter().position(|h| h == name);
+// ── Import ──
+
+fn cmd_import(input: PathBuf, store: Store) -> Result<()> {
+    let contents = std::fs::read_to_string(&input)
+        .with_context(|| format!("Failed to read {}", input.display()))?;
+    let doc = roxmltree::Document::parse(&contents)
+        .with_context(|| format!("Failed to parse {}", input.display()))?;
+    let imported = match doc.root_element().tag_name().name() {
+        "uddf" => uddf::parse_uddf(&contents)?,
+        "divelog" => subsurface::parse_ssrf(&contents)?,
+        other => anyhow::bail!(
+            "{}: unknown logbook format (root element <{}>), expected UDDF or Subsurface XML",
+            input.display(),
+            other
+        ),
+    };
 
-    let date_col = col("Date / Temps").unwrap_or(3);
-    let site_col = col("Site de plongée").unwrap_or(1);
-    let country_col = col("Pays").unwrap_or(2);
-    let buddy_col = col("Equipier / Instructor / Center").unwrap_or(9);
+    let existing = if store.exists() {
+        store.load()?.dives
+    } else {
+        Vec::new()
+    };
 
-    let mut records = Vec::new();
-    for (line_num, line) in lines.enumerate() {
-        let fields = parse_csv_line(line);
-        let max_col = *[date_col, site_col, country_col, buddy_col]
-            .iter()
-            .max()
-            .unwrap();
-        if fields.len() <= max_col {
+    let source = input
+        .file_name()
+        .unwrap_or(input.as_os_str())
+        .to_string_lossy()
+        .to_string();
+    let total = imported.len();
+    let mut added: Vec<DiveLog> = Vec::new();
+    for mut dive in imported {
+        // Same dive from another computer, or this logbook imported before
+        if let Some(existing) = existing.iter().chain(&added).find(|d| d.overlaps(&dive)) {
             eprintln!(
-                "Warning: skipping CSV line {} (not enough fields)",
-                line_num + 2
+                "  {} ({:.1}m): overlaps dive #{} ({}), skipping",
+                dive.datetime.format("%Y-%m-%d %H:%M"),
+                dive.max_depth_m,
+                existing.number,
+                existing.source.as_deref().unwrap_or("device"),
             );
             continue;
         }
+        eprintln!(
+            "  Dive #{}: {} | {:.1}m | {}min | {} samples",
+            dive.number,
+            dive.datetime.format("%Y-%m-%d %H:%M"),
+            dive.max_depth_m,
+            dive.duration_seconds / 60,
+            dive.samples.len(),
+        );
+        dive.source = Some(source.clone());
+        added.push(dive);
+    }
 
-        let datetime = match chrono::NaiveDateTime::parse_from_str(
-            fields[date_col].trim(),
-            "%d. %b %Y %H:%M",
-        ) {
-            Ok(dt) => dt,
-            Err(e) => {
-                eprintln!(
-                    "Warning: skipping CSV line {} (bad date {:?}: {})",
-                    line_num + 2,
-                    fields[date_col],
-                    e
-                );
-                continue;
-            }
-        };
+    store.save_dives(&added)?;
+    eprintln!(
+        "Imported {} of {} dive(s) from {} into {}",
+        added.len(),
+        total,
+        input.display(),
+        store.path().display()
+    );
 
-        records.push(SsiRecord {
-            datetime,
-            site: fields[site_col].trim().to_string(),
-            country: fields[country_col].trim().to_string(),
-            buddy: clean_buddy(&fields[buddy_col]),
-        });
-    }
+    Ok(())
+}
+
+// ── Migrate ──
+
+fn cmd_migrate(json: PathBuf, db: Option<PathBuf>) -> Result<()> {
+    let Some(db) = db else {
+        anyhow::bail!("migrate needs the target database, e.g. `sirius-dive --db dives.db migrate`");
+    };
+    let data = Store::Json(json.clone()).load()?;
+    let store = Store::Sqlite(db);
+    store.save_dives(&data.dives)?;
+    eprintln!(
+        "Copied {} dive(s) from {} to {}",
+        data.dives.len(),
+        json.display(),
+        store.path().display()
+    );
+    Ok(())
+}
+
+// ── Correlate ──
 
-    records
+struct CorrelateOptions {
+    tolerance: i64,
+    shift: i64,
+    policy: MergePolicy,
+    interactive: bool,
+    dry_run: bool,
 }
 
-fn cmd_correlate(csv_path: PathBuf, json_path: PathBuf) -> Result<()> {
-    use chrono::{Datelike, Timelike};
+/// Answer to the conflict prompt; "all" and "none" stick for the rest of
+/// the run.
+#[derive(PartialEq)]
+enum Confirm {
+    Ask,
+    All,
+    None,
+}
 
-    // Load dives.json
-    let json_contents = std::fs::read_to_string(&json_path)
-        .with_context(|| format!("Failed to read {}", json_path.display()))?;
-    let mut data: DiveData = serde_json::from_str(&json_contents)
-        .with_context(|| format!("Failed to parse {}", json_path.display()))?;
+fn confirm(state: &mut Confirm, question: &str) -> Result<bool> {
+    use std::io::Write;
+
+    loop {
+        match state {
+            Confirm::All => return Ok(true),
+            Confirm::None => return Ok(false),
+            Confirm::Ask => {}
+        }
+        eprint!("{} [y]es / [n]o / [a]ll / n[o]ne: ", question);
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer)? == 0 {
+            anyhow::bail!("No answer on stdin; aborting without changes");
+        }
+        match answer.trim().to_lowercase().as_str() {
+            "y" | "yes" => return Ok(true),
+            "n" | "no" | "" => return Ok(false),
+            "a" | "all" => *state = Confirm::All,
+            "o" | "none" => *state = Confirm::None,
+            _ => {}
+        }
+    }
+}
+
+fn quoted(value: Option<&str>) -> String {
+    match value {
+        Some(v) if !v.is_empty() => format!("{:?}", v),
+        _ => "(empty)".to_string(),
+    }
+}
+
+fn cmd_correlate(
+    csv_path: PathBuf,
+    store: Store,
+    mapping: Option<ssi::SsiMapping>,
+    options: &CorrelateOptions,
+) -> Result<()> {
+    // Load dives
+    let mut data = store.load()?;
 
     // Parse SSI CSV
     let csv_contents = std::fs::read_to_string(&csv_path)
         .with_context(|| format!("Failed to read {}", csv_path.display()))?;
-    let ssi_records = parse_ssi_csv(&csv_contents);
+    let ssi_records = ssi::parse_ssi_csv(&csv_contents, mapping)
+        .with_context(|| format!("Failed to parse {}", csv_path.display()))?;
     eprintln!("Parsed {} SSI record(s) from {}", ssi_records.len(), csv_path.display());
 
-    // Build lookup by (year, month, day, hour, minute)
-    let lookup: HashMap<(i32, u32, u32, u32, u32), &SsiRecord> = ssi_records
-        .iter()
-        .map(|r| {
-            let key = (
-                r.datetime.date().year(),
-                r.datetime.date().month(),
-                r.datetime.date().day(),
-                r.datetime.time().hour(),
-                r.datetime.time().minute(),
-            );
-            (key, r)
-        })
-        .collect();
+    // SSI records and dive computer times are both local wall-clock times;
+    // --shift covers an SSI log kept in another zone.
+    let matching = ssi::match_dives(&data.dives, &ssi_records, options.tolerance, options.shift);
+
+    let describe = |r: &ssi::SsiRecord| {
+        format!(
+            "SSI line {} ({} {})",
+            r.row,
+            r.datetime.format("%Y-%m-%d %H:%M"),
+            r.site
+        )
+    };
+    let mut updated: Vec<DiveLog> = Vec::new();
+    let mut prompt = Confirm::Ask;
+    for &(d, r, minutes) in &matching.matched {
+        let dive = &mut data.dives[d];
+        let ssi = &ssi_records[r];
+        let mut merge = Merge {
+            options,
+            prompt: &mut prompt,
+            has_values: dive.site.is_some()
+                || dive.country.is_some()
+                || dive.buddy.is_some()
+                || dive.notes.is_some()
+                || !dive.details.is_empty(),
+            header: Some(format!(
+                "Dive #{} ({}) <- {}{}",
+                dive.number,
+                dive.start().0.format("%Y-%m-%d %H:%M"),
+                describe(ssi),
+                if minutes != 0 { format!(" ({:+} min)", minutes) } else { String::new() }
+            )),
+            changed: false,
+        };
 
-    let mut matched = 0u32;
-    let mut unmatched = 0u32;
+        let text = |s: &String| (!s.is_empty()).then(|| s.clone());
+        let new = &ssi.details;
+        let details = &mut dive.details;
+        merge.field("site", &mut dive.site, text(&ssi.site), String::clone)?;
+        merge.field("country", &mut dive.country, text(&ssi.country), String::clone)?;
+        merge.field("buddy", &mut dive.buddy, text(&ssi.buddy), String::clone)?;
+        merge.field("type", &mut details.dive_type, new.dive_type.clone(), String::clone)?;
+        merge.field("water", &mut details.water_body, new.water_body.clone(), String::clone)?;
+        merge.field("water type", &mut details.water_type, new.water_type.clone(), String::clone)?;
+        merge.field("visibility", &mut details.visibility_m, new.visibility_m, |v| format!("{:.0} m", v))?;
+        merge.field("weather", &mut details.weather, new.weather.clone(), String::clone)?;
+        merge.field("air temp", &mut details.air_temp_c, new.air_temp_c, |t| format!("{:.1} °C", t))?;
+        merge.field("entry", &mut details.entry, new.entry.clone(), String::clone)?;
+        merge.field("weight", &mut details.weight_kg, new.weight_kg, |w| format!("{:.1} kg", w))?;
+        merge.field("rating", &mut details.rating, new.rating, |r| format!("{}/5", r))?;
+        merge.field("notes", &mut dive.notes, text(&ssi.notes), String::clone)?;
+        if merge.changed {
+            updated.push(dive.clone());
+        }
+    }
 
-    for dive in &mut data.dives {
-        let key = (
-            dive.datetime.date().year(),
-            dive.datetime.date().month(),
-            dive.datetime.date().day(),
-            dive.datetime.time().hour(),
-            dive.datetime.time().minute(),
-        );
+    eprintln!(
+        "Matched: {}, Ambiguous: {}, Unmatched dives: {}, Unmatched SSI entries: {}",
+        matching.matched.len(),
+        matching.ambiguous.len(),
+        matching.unmatched_dives.len(),
+        matching.unmatched_records.len()
+    );
+    for (dives, records) in &matching.ambiguous {
+        let numbers: Vec<String> = dives
+            .iter()
+            .map(|&d| format!("#{}", data.dives[d].number))
+            .collect();
+        eprintln!("\nAmbiguous, left unchanged: dive {} with", numbers.join(", "));
+        for &r in records {
+            eprintln!("  {}", describe(&ssi_records[r]));
+        }
+    }
+    if !matching.unmatched_dives.is_empty() {
+        eprintln!("\nDives without an SSI entry:");
+        for &d in &matching.unmatched_dives {
+            let dive = &data.dives[d];
+            eprintln!("  #{} {}", dive.number, dive.start().0.format("%Y-%m-%d %H:%M"));
+        }
+    }
+    if !matching.unmatched_records.is_empty() {
+        eprintln!("\nSSI entries without a dive:");
+        for &r in &matching.unmatched_records {
+            eprintln!("  {}", describe(&ssi_records[r]));
+        }
+    }
 
-        if let Some(ssi) = lookup.get(&key) {
-            if !ssi.site.is_empty() {
-                dive.site = Some(ssi.site.clone());
-            }
-            if !ssi.country.is_empty() {
-                dive.country = Some(ssi.country.clone());
-            }
-            if !ssi.buddy.is_empty() {
-                dive.buddy = Some(ssi.buddy.clone());
-            }
-            matched += 1;
+    if options.dry_run {
+        eprintln!("\nDry run: {} dive(s) would be updated, nothing written", updated.len());
+        return Ok(());
+    }
+    if updated.is_empty() {
+        eprintln!("\nNothing to update");
+        return Ok(());
+    }
+
+    // Write back
+    let backup = store.backup()?;
+    eprintln!("\nBackup saved to {}", backup.display());
+    store.save_dives(&updated)?;
+    eprintln!("Updated {} dive(s) in {}", updated.len(), store.path().display());
+
+    Ok(())
+}
+
+/// Applies SSI values to one dive's fields according to the correlate options,
+/// printing each change under the dive's header.
+struct Merge<'a> {
+    options: &'a CorrelateOptions,
+    prompt: &'a mut Confirm,
+    /// Whether the dive already had any correlated field (for `--policy keep`).
+    has_values: bool,
+    /// Printed before the first change, then cleared.
+    header: Option<String>,
+    changed: bool,
+}
+
+impl Merge<'_> {
+    fn field<T: PartialEq>(
+        &mut self,
+        name: &str,
+        current: &mut Option<T>,
+        new: Option<T>,
+        show: fn(&T) -> String,
+    ) -> Result<()> {
+        let Some(new) = new else {
+            return Ok(());
+        };
+        if current.as_ref() == Some(&new) {
+            return Ok(());
+        }
+        if let Some(header) = self.header.take() {
+            eprintln!("{}", header);
+        }
+
+        let conflict = current.as_ref().is_some_and(|c| !show(c).is_empty());
+        let diff = format!(
+            "  {:<11} {} -> {}",
+            format!("{}:", name),
+            quoted(current.as_ref().map(show).as_deref()),
+            quoted(Some(&show(&new)))
+        );
+        let asked = conflict && self.options.interactive && *self.prompt == Confirm::Ask;
+        let apply = if conflict && self.options.interactive {
+            confirm(self.prompt, &format!("{}?", diff))?
         } else {
-            unmatched += 1;
+            match self.options.policy {
+                MergePolicy::Overwrite => true,
+                MergePolicy::FillEmpty => !conflict,
+                MergePolicy::Keep => !self.has_values,
+            }
+        };
+        if !asked {
+            eprintln!("{}{}", diff, if apply { "" } else { "  (kept)" });
+        }
+        if apply {
+            *current = Some(new);
+            self.changed = true;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a time shift: "+01:00", "-2", "+0130" or "90m".
+fn parse_shift(s: &str) -> Result<i64, String> {
+    let err = || format!("invalid shift {s:?} (expected e.g. \"+01:00\" or \"-90m\")");
+    let s = s.trim();
+    if let Some(minutes) = s.strip_suffix('m') {
+        return minutes.parse().map_err(|_| err());
+    }
+    let (sign, rest) = match s.as_bytes().first() {
+        Some(b'-') => (-1, &s[1..]),
+        Some(b'+') => (1, &s[1..]),
+        _ => (1, s),
+    };
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if rest.len() == 4 => rest.split_at(2),
+        None => (rest, "0"),
+    };
+    let hours: i64 = hours.parse().map_err(|_| err())?;
+    let minutes: i64 = minutes.parse().map_err(|_| err())?;
+    if minutes >= 60 {
+        return Err(err());
+    }
+    Ok(sign * (hours * 60 + minutes))
+}
+
+// ── Set time zone ──
+
+fn cmd_set_tz(
+    tz: Option<DiveTz>,
+    from: Option<chrono::NaiveDate>,
+    to: Option<chrono::NaiveDate>,
+    store: Store,
+) -> Result<()> {
+    let data = store.load()?;
+
+    let mut updated = Vec::new();
+    for mut dive in data.dives {
+        let date = dive.datetime.date();
+        if from.is_some_and(|f| date < f) || to.is_some_and(|t| date > t) {
+            continue;
         }
+        dive.timezone = tz;
+        updated.push(dive);
     }
 
-    eprintln!("Matched: {}, Unmatched: {}", matched, unmatched);
+    match tz {
+        Some(tz) => eprintln!("Set time zone {tz} on {} dive(s)", updated.len()),
+        None => eprintln!("Cleared time zone on {} dive(s)", updated.len()),
+    }
 
-    // Write back
-    let json = serde_json::to_string_pretty(&data)?;
-    std::fs::write(&json_path, &json)?;
-    eprintln!("Updated {}", json_path.display());
+    store.save_dives(&updated)?;
+    eprintln!("Updated {}", store.path().display());
 
     Ok(())
 }
@@ -848,7 +1663,7 @@ fn find_overlapping_dive(
     let mut best: Option<(&DiveLog, i64)> = None;
 
     for dive in dives {
-        let dive_start = dive.datetime + chrono::Duration::seconds(offset);
+        let dive_start = dive.start_utc() + chrono::Duration::seconds(offset);
         let dive_end = dive_start + chrono::Duration::seconds(dive.duration_seconds as i64);
 
         let overlap_start = video_start.max(dive_start);
@@ -862,12 +1677,22 @@ fn find_overlapping_dive(
 
     match best {
         Some((dive, overlap)) => {
+            let (start, precision) = dive.start();
             eprintln!(
                 "Matched dive #{} ({}) — {:.0}s overlap",
                 dive.number,
-                dive.datetime.format("%Y-%m-%d %H:%M"),
+                match precision {
+                    TimePrecision::Second => start.format("%Y-%m-%d %H:%M:%S"),
+                    TimePrecision::Minute => start.format("%Y-%m-%d %H:%M"),
+                },
                 overlap
             );
+            if precision == TimePrecision::Minute {
+                eprintln!("  Dive start only known to the minute; use --offset to fine-tune.");
+            }
+            if dive.timezone.is_none() {
+                eprintln!("  Dive has no time zone; assuming its clock is UTC (see set-tz).");
+            }
             Ok(dive)
         }
         None => {
@@ -875,19 +1700,20 @@ fn find_overlapping_dive(
             eprintln!("Video time range: {} to {}", video_start, video_end);
             let same_day: Vec<_> = dives
                 .iter()
-                .filter(|d| d.datetime.date() == video_date)
+                .filter(|d| d.start_utc().date() == video_date)
                 .collect();
             if same_day.is_empty() {
                 eprintln!("No dives found on {video_date}.");
             } else {
-                eprintln!("Dives on {video_date}:");
+                eprintln!("Dives on {video_date} (UTC):");
                 for dive in &same_day {
+                    let dive_start = dive.start_utc();
                     let dive_end =
-                        dive.datetime + chrono::Duration::seconds(dive.duration_seconds as i64);
+                        dive_start + chrono::Duration::seconds(dive.duration_seconds as i64);
                     eprintln!(
                         "  #{}: {} to {}",
                         dive.number,
-                        dive.datetime.format("%H:%M:%S"),
+                        dive_start.format("%H:%M:%S"),
                         dive_end.format("%H:%M:%S")
                     );
                 }
@@ -914,7 +1740,7 @@ fn build_drawtext_filter(
     offset: i64,
     video_height: u32,
 ) -> String {
-    let dive_start = dive.datetime + chrono::Duration::seconds(offset);
+    let dive_start = dive.start_utc() + chrono::Duration::seconds(offset);
     let dive_start_offset = (video_start - dive_start).num_seconds();
 
     // Scale overlay relative to 1080p baseline
@@ -925,6 +1751,7 @@ fn build_drawtext_filter(
     let margin = (20.0 * scale).round() as u32;
 
     let mut filters = Vec::new();
+    let pressures = dive.sample_pressures(PressureFill::Linear);
 
     for (i, sample) in dive.samples.iter().enumerate() {
         let sample_video_t = sample.time_s as f64 - dive_start_offset as f64;
@@ -948,8 +1775,13 @@ fn build_drawtext_filter(
         if let Some(temp) = sample.temp_c {
             text.push_str(&format!("  {temp:.1}°C"));
         }
-        if let Some(pressure) = sample.pressure_bar {
-            text.push_str(&format!("  {pressure:.0}bar"));
+        let tank_values: Vec<String> = pressures
+            .iter()
+            .filter_map(|(_, values)| values[i])
+            .map(|p| format!("{p:.0}"))
+            .collect();
+        if !tank_values.is_empty() {
+            text.push_str(&format!("  {}bar", tank_values.join("/")));
         }
 
         let escaped = escape_drawtext(&text);
@@ -972,15 +1804,12 @@ fn build_drawtext_filter(
     filters.join(",")
 }
 
-fn cmd_watermark(video: PathBuf, json: PathBuf, offset: i64) -> Result<()> {
+fn cmd_watermark(video: PathBuf, store: Store, offset: i64) -> Result<()> {
     // Load dives
-    let json_contents = std::fs::read_to_string(&json)
-        .with_context(|| format!("Failed to read {}", json.display()))?;
-    let data: DiveData = serde_json::from_str(&json_contents)
-        .with_context(|| format!("Failed to parse {}", json.display()))?;
+    let data = store.load()?;
 
     if data.dives.is_empty() {
-        anyhow::bail!("No dives found in {}", json.display());
+        anyhow::bail!("No dives found in {}", store.path().display());
     }
 
     // Probe video
@@ -1003,9 +1832,12 @@ fn cmd_watermark(video: PathBuf, json: PathBuf, offset: i64) -> Result<()> {
     // Build filter
     let filter = build_drawtext_filter(dive, meta.capture_time, meta.duration_secs, offset, meta.height);
 
-    // Build output path: YYYY-MM-DD_HHhMM_Site_Name.ext
+    // Build output path: YYYY-MM-DD_HHhMM_Site_Name.ext (dive local time)
     let ext = video.extension().unwrap_or_default().to_string_lossy();
-    let dt_str = meta.capture_time.format("%Y-%m-%d_%Hh%M").to_string();
+    let dt_str = dive
+        .to_local(meta.capture_time)
+        .format("%Y-%m-%d_%Hh%M")
+        .to_string();
     let output_name = match &dive.site {
         Some(site) if !site.is_empty() => {
             let safe_site = site.replace(' ', "_");
@@ -1089,3 +1921,26 @@ async fn find_device(
 
     Ok(dev.peripheral)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reimporting_a_logbook_skips_its_dives() {
+        let dir = std::env::temp_dir().join(format!("sirius-import-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/imperial.ssrf");
+        let json = dir.join("dives.json");
+
+        cmd_import(input.clone(), Store::Json(json.clone())).unwrap();
+        cmd_import(input, Store::Json(json.clone())).unwrap();
+
+        let dives = Store::Json(json).load().unwrap().dives;
+        let numbers: Vec<u32> = dives.iter().map(|d| d.number).collect();
+        assert_eq!(numbers, [7, 41]);
+        assert!(dives.iter().all(|d| d.source.as_deref() == Some("imperial.ssrf")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
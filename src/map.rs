@@ -0,0 +1,222 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use crate::types::DiveLog;
+use crate::xml::XmlWriter;
+
+/// Coordinates of a dive site from the user's site table.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SiteCoords {
+    pub lat: f64,
+    pub lon: f64,
+    /// Only match dives in this country (for site names used in several).
+    #[serde(default)]
+    pub country: Option<String>,
+}
+
+/// Site name -> coordinates, keyed by the lowercased name.
+#[derive(Default)]
+pub struct CoordTable(HashMap<String, Vec<SiteCoords>>);
+
+fn key(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+impl CoordTable {
+    /// Load a table from CSV (`site,lat,lon[,country]` with a header row) or
+    /// TOML (`["Site name"]` tables with `lat`, `lon` and optional `country`),
+    /// chosen by the file extension.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let entries = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => {
+                let table: BTreeMap<String, SiteCoords> = toml::from_str(&contents)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?;
+                table.into_iter().collect()
+            }
+            _ => parse_csv(&contents).with_context(|| format!("Failed to parse {}", path.display()))?,
+        };
+
+        let mut table: HashMap<String, Vec<SiteCoords>> = HashMap::new();
+        for (name, coords) in entries {
+            if !(-90.0..=90.0).contains(&coords.lat) || !(-180.0..=180.0).contains(&coords.lon) {
+                anyhow::bail!("{}: coordinates of {:?} out of range", path.display(), name);
+            }
+            table.entry(key(&name)).or_default().push(coords);
+        }
+        Ok(CoordTable(table))
+    }
+
+    /// Coordinates for a site, preferring an entry for the dive's country.
+    fn lookup(&self, site: &str, country: Option<&str>) -> Option<&SiteCoords> {
+        let candidates = self.0.get(&key(site))?;
+        let same_country = |c: &&SiteCoords| match (&c.country, country) {
+            (Some(a), Some(b)) => key(a) == key(b),
+            _ => false,
+        };
+        candidates
+            .iter()
+            .find(same_country)
+            .or_else(|| candidates.iter().find(|c| c.country.is_none()))
+    }
+}
+
+fn parse_csv(contents: &str) -> Result<Vec<(String, SiteCoords)>> {
+    let mut rows = crate::ssi::parse_csv(contents, ',').into_iter();
+    let header: Vec<String> = rows
+        .next()
+        .unwrap_or_default()
+        .iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+    let site_col = column(&["site", "name"]).context("missing site column")?;
+    let lat_col = column(&["lat", "latitude"]).context("missing lat column")?;
+    let lon_col = column(&["lon", "lng", "longitude"]).context("missing lon column")?;
+    let country_col = column(&["country"]);
+
+    let mut entries = Vec::new();
+    for (i, fields) in rows.enumerate() {
+        if fields.iter().all(|f| f.trim().is_empty()) {
+            continue;
+        }
+        let field = |col: usize| fields.get(col).map(|f| f.trim()).unwrap_or("");
+        let number = |col: usize| {
+            field(col)
+                .parse::<f64>()
+                .with_context(|| format!("line {}: invalid coordinate {:?}", i + 2, field(col)))
+        };
+        entries.push((
+            field(site_col).to_string(),
+            SiteCoords {
+                lat: number(lat_col)?,
+                lon: number(lon_col)?,
+                country: country_col
+                    .map(field)
+                    .filter(|c| !c.is_empty())
+                    .map(str::to_string),
+            },
+        ));
+    }
+    Ok(entries)
+}
+
+/// Dives aggregated per site.
+pub struct SiteSummary<'a> {
+    pub name: &'a str,
+    pub country: Option<&'a str>,
+    pub coords: Option<SiteCoords>,
+    pub dives: Vec<&'a DiveLog>,
+}
+
+impl SiteSummary<'_> {
+    fn deepest(&self) -> &DiveLog {
+        self.dives
+            .iter()
+            .max_by(|a, b| a.max_depth_m.total_cmp(&b.max_depth_m))
+            .unwrap()
+    }
+
+    fn dates(&self) -> Vec<NaiveDate> {
+        let mut dates: Vec<NaiveDate> = self.dives.iter().map(|d| d.start().0.date()).collect();
+        dates.sort();
+        dates.dedup();
+        dates
+    }
+
+    fn title(&self) -> String {
+        match self.country {
+            Some(country) => format!("{}, {}", self.name, country),
+            None => self.name.to_string(),
+        }
+    }
+
+    fn description(&self) -> String {
+        let deepest = self.deepest();
+        let dates: Vec<String> = self.dates().iter().map(|d| d.to_string()).collect();
+        format!(
+            "{} dive{}\nDeepest: {:.1} m (dive #{})\nDates: {}",
+            self.dives.len(),
+            if self.dives.len() == 1 { "" } else { "s" },
+            deepest.max_depth_m,
+            deepest.number,
+            dates.join(", ")
+        )
+    }
+}
+
+/// Group dives by site and country, resolving coordinates from `table`.
+pub fn summarize<'a>(dives: &'a [DiveLog], table: &CoordTable) -> Vec<SiteSummary<'a>> {
+    let mut groups: BTreeMap<(&str, Option<&str>), Vec<&DiveLog>> = BTreeMap::new();
+    for dive in dives {
+        if let Some(site) = dive.site.as_deref() {
+            groups.entry((site, dive.country.as_deref())).or_default().push(dive);
+        }
+    }
+    groups
+        .into_iter()
+        .map(|((name, country), dives)| SiteSummary {
+            name,
+            country,
+            coords: table.lookup(name, country).cloned(),
+            dives,
+        })
+        .collect()
+}
+
+/// GPX 1.1 document with a waypoint per located site.
+pub fn sites_to_gpx(sites: &[SiteSummary]) -> String {
+    let mut xml = XmlWriter::new();
+    xml.open(
+        "gpx",
+        &[
+            ("version", "1.1"),
+            ("creator", env!("CARGO_PKG_NAME")),
+            ("xmlns", "http://www.topografix.com/GPX/1/1"),
+        ],
+    );
+    for site in sites {
+        let Some(ref coords) = site.coords else {
+            continue;
+        };
+        xml.open(
+            "wpt",
+            &[("lat", &format!("{:.6}", coords.lat)), ("lon", &format!("{:.6}", coords.lon))],
+        );
+        xml.leaf("name", &[], &site.title());
+        xml.leaf("desc", &[], &site.description());
+        xml.leaf("sym", &[], "Scuba Diving");
+        xml.leaf("type", &[], "Dive site");
+        xml.close();
+    }
+    xml.close();
+    xml.finish()
+}
+
+/// KML 2.2 document with a placemark per located site.
+pub fn sites_to_kml(sites: &[SiteSummary]) -> String {
+    let mut xml = XmlWriter::new();
+    xml.open("kml", &[("xmlns", "http://www.opengis.net/kml/2.2")]);
+    xml.open("Document", &[]);
+    xml.leaf("name", &[], "Dive sites");
+    for site in sites {
+        let Some(ref coords) = site.coords else {
+            continue;
+        };
+        xml.open("Placemark", &[]);
+        xml.leaf("name", &[], &site.title());
+        xml.leaf("description", &[], &site.description());
+        xml.open("Point", &[]);
+        xml.leaf("coordinates", &[], &format!("{:.6},{:.6}", coords.lon, coords.lat));
+        xml.close();
+        xml.close();
+    }
+    xml.close();
+    xml.close();
+    xml.finish()
+}
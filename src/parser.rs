@@ -1,6 +1,9 @@
+use std::collections::BTreeMap;
+
 use anyhow::{bail, Result};
 use chrono::{NaiveDate, NaiveDateTime};
 
+use crate::freedive;
 use crate::types::*;
 
 /// Read a u16 from a byte slice at the given offset (little-endian).
@@ -43,6 +46,44 @@ fn decode_genius_datetime(packed: u32) -> NaiveDateTime {
         })
 }
 
+/// Decode a signed temperature in 1/10 deg C.
+///
+/// The sensor reports signed values (ice dives go below 0 C). Returns `None`
+/// for the i16::MIN / i16::MAX sentinels and for values outside the range a
+/// water temperature sensor can physically report.
+fn decode_temperature(raw: u16) -> Option<f64> {
+    let raw = raw as i16;
+    if raw == i16::MIN || raw == i16::MAX {
+        return None;
+    }
+    let temp_c = raw as f64 / 10.0;
+    (TEMP_MIN_C..=TEMP_MAX_C).contains(&temp_c).then_some(temp_c)
+}
+
+/// Decode a DPRS sample temperature. The original parser read a raw 0 as
+/// "no reading"; it is only taken as 0.0 deg C when `zero_is_reading`, i.e.
+/// when the header range says the water got that cold.
+fn decode_sample_temperature(raw: u16, zero_is_reading: bool) -> Option<f64> {
+    if raw == 0 && !zero_is_reading {
+        return None;
+    }
+    decode_temperature(raw)
+}
+
+/// Whether a raw 0 sample temperature can be a real reading: the header
+/// minimum is at or below 0 deg C. A header without a valid range, or with
+/// both fields 0, means no sensor data.
+fn zero_temp_is_reading(min_temp_c: Option<f64>, max_temp_c: Option<f64>) -> bool {
+    match (min_temp_c, max_temp_c) {
+        (Some(min), Some(max)) => min <= 0.0 && !(min == 0.0 && max == 0.0),
+        _ => false,
+    }
+}
+
+/// Plausible water temperature range; anything outside is a bad reading.
+const TEMP_MIN_C: f64 = -10.0;
+const TEMP_MAX_C: f64 = 50.0;
+
 /// Extract the dive number from a raw 200-byte header without doing a full parse.
 /// The dive number is at offset 0x04 as a u32 LE.
 pub fn dive_number_from_header(header: &[u8]) -> u32 {
@@ -53,7 +94,232 @@ pub fn dive_number_from_header(header: &[u8]) -> u32 {
     }
 }
 
+/// Parse a raw dump, dispatching on the model's data layout: GENIUS-family
+/// models use the ECOP header + tagged profile, older models the iconhd
+/// layouts. Dives downloaded over BLE always arrive as ECOP objects and go
+/// straight to `parse_dive_ecop`.
+pub fn parse_dive(model: Model, dive_index: u32, header: &[u8], profile: &[u8]) -> Result<DiveLog> {
+    if model.is_genius() {
+        parse_dive_ecop(dive_index, header, profile)
+    } else {
+        parse_dive_iconhd(model, dive_index, header, profile)
+    }
+}
+
+/// A freedive "dive" is a whole session; keep the individual breath-holds.
+fn session_apneas(dive_mode: DiveMode, samples: &[Sample]) -> Vec<Apnea> {
+    if dive_mode == DiveMode::Freedive {
+        freedive::split_apneas(samples)
+    } else {
+        Vec::new()
+    }
+}
+
+/// Pre-GENIUS dive layout (libdivecomputer mares_iconhd_parser.c).
+///
+/// A dive is stored as `[u32 LE length][samples][header]`, with the header at
+/// the end. The header size and sample size depend on the model; air
+/// integrated models add a tank pressure to every sample.
+struct IconHdLayout {
+    header_size: usize,
+    sample_size: usize,
+    /// Offset of the tank table (begin/end pressures) on air-integrated models.
+    tanks: Option<usize>,
+}
+
+/// Icon HD, Puck Pro, Nemo Wide 2, Puck 2, Quad.
+const LAYOUT_ICONHD: IconHdLayout = IconHdLayout {
+    header_size: 0x5C,
+    sample_size: 8,
+    tanks: None,
+};
+
+/// Icon AIR (Icon HD Net Ready).
+const LAYOUT_ICONHDNET: IconHdLayout = IconHdLayout {
+    header_size: 0x80,
+    sample_size: 12,
+    tanks: Some(0x58),
+};
+
+/// Quad Air, Smart Air, Puck Air 2.
+const LAYOUT_QUADAIR: IconHdLayout = IconHdLayout {
+    header_size: 0x84,
+    sample_size: 12,
+    tanks: Some(0x58),
+};
+
+fn iconhd_layout(model: Model) -> &'static IconHdLayout {
+    match model {
+        Model::IconAir => &LAYOUT_ICONHDNET,
+        Model::QuadAir | Model::SmartAir | Model::PuckAir2 => &LAYOUT_QUADAIR,
+        _ => &LAYOUT_ICONHD,
+    }
+}
+
+/// Split a raw iconhd-family dive (`[length][samples][header]`) into its
+/// header and sample data, as expected by `parse_dive_iconhd`.
+pub fn split_iconhd_dive(model: Model, data: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+    let layout = iconhd_layout(model);
+    if data.len() < 4 + layout.header_size {
+        bail!("Dive data too short: {} bytes", data.len());
+    }
+    let length = read_u32_le(data, 0) as usize;
+    if length > data.len() {
+        bail!("Truncated dive: length field is {length} bytes, only {} present", data.len());
+    }
+    if length < 4 + layout.header_size {
+        bail!("Invalid dive length {length}");
+    }
+    let header_start = length - layout.header_size;
+    Ok((
+        data[header_start..length].to_vec(),
+        data[4..header_start].to_vec(),
+    ))
+}
+
+/// Parse a dive in the pre-GENIUS (iconhd-family) layout.
+///
+/// Header layout (offsets into the header block, from libdivecomputer):
+///   0x00: settings (u16 LE), bits 0-1 = mode (0=Air, 1=Gauge, 2=Nitrox, 3=Freedive)
+///   0x02: nsamples (u16 LE)
+///   0x04: maxdepth (u16 LE, 1/10 m)
+///   0x06: datetime (5 x u16 LE: hour, minute, day, month-1, year-1900)
+///   0x10: gas mixes (3 entries, 4 bytes each, byte 0 = O2 %)
+///   0x42: temperature_min (i16 LE, 1/10 C)
+///   0x44: temperature_max (i16 LE, 1/10 C)
+///   0x58: tanks on air-integrated models (3 entries, 8 bytes each:
+///         begin pressure, end pressure (u16 LE, 1/100 bar), volume, working pressure)
+///
+/// Samples (5 s interval, 1 s in freedive mode):
+///   +0: depth (u16 LE, 1/10 m)
+///   +2: temperature (i16 LE, 1/10 C)
+///   +4: alarms / misc (u32 LE)
+///   +8: tank pressure (u16 LE, 1/100 bar), 12-byte samples only
+pub fn parse_dive_iconhd(
+    model: Model,
+    dive_index: u32,
+    header: &[u8],
+    profile: &[u8],
+) -> Result<DiveLog> {
+    let layout = iconhd_layout(model);
+    if header.len() < layout.header_size {
+        bail!(
+            "Dive header too short: {} bytes (expected {})",
+            header.len(),
+            layout.header_size
+        );
+    }
+
+    let settings = read_u16_le(header, 0x00);
+    let dive_mode = match settings & 0x03 {
+        0 => DiveMode::Air,
+        1 => DiveMode::Gauge,
+        2 => DiveMode::Nitrox,
+        _ => DiveMode::Freedive,
+    };
+
+    let nsamples = read_u16_le(header, 0x02) as usize;
+    let max_depth_m = read_u16_le(header, 0x04) as f64 / 10.0;
+
+    let field = |i: usize| read_u16_le(header, 0x06 + i * 2) as u32;
+    let datetime = NaiveDate::from_ymd_opt(field(4) as i32 + 1900, field(3) + 1, field(2))
+        .and_then(|d| d.and_hms_opt(field(0), field(1), 0))
+        .unwrap_or_else(|| {
+            NaiveDate::from_ymd_opt(2000, 1, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+        });
+
+    let mut gas_mixes = Vec::new();
+    if dive_mode == DiveMode::Nitrox {
+        for i in 0..3 {
+            let o2 = header[0x10 + i * 4];
+            if o2 > 0 && o2 <= 100 {
+                gas_mixes.push(GasMix { o2 });
+            }
+        }
+    }
+    if gas_mixes.is_empty() {
+        gas_mixes.push(GasMix { o2: 21 });
+    }
+
+    let min_temp_c = decode_temperature(read_u16_le(header, 0x42));
+    let max_temp_c = decode_temperature(read_u16_le(header, 0x44));
+
+    let sample_interval = if dive_mode == DiveMode::Freedive { 1 } else { 5 };
+
+    let mut samples = Vec::new();
+    let mut readings = Vec::new();
+    for (i, record) in profile
+        .chunks_exact(layout.sample_size)
+        .take(nsamples)
+        .enumerate()
+    {
+        let time_s = i as u32 * sample_interval;
+        let depth_m = read_u16_le(record, 0) as f64 / 10.0;
+        let temp_c = decode_temperature(read_u16_le(record, 2));
+
+        if layout.sample_size >= 12 {
+            let pressure_raw = read_u16_le(record, 8);
+            if pressure_raw > 0 {
+                readings.push(PressureReading {
+                    time_s,
+                    pressure_bar: pressure_raw as f64 / 100.0,
+                });
+            }
+        }
+
+        samples.push(Sample {
+            time_s,
+            depth_m,
+            temp_c,
+            pressure_bar: None,
+        });
+    }
+
+    // Air-integrated models: first tank entry holds the transmitter pressures
+    let mut tanks = Vec::new();
+    if let Some(offset) = layout.tanks {
+        let begin = read_u16_le(header, offset);
+        let end = read_u16_le(header, offset + 2);
+        if begin > 0 || end > 0 || !readings.is_empty() {
+            tanks.push(Tank {
+                index: 0,
+                gas_mix: Some(0),
+                begin_pressure_bar: (begin > 0).then_some(begin as f64 / 100.0),
+                end_pressure_bar: (end > 0).then_some(end as f64 / 100.0),
+                readings,
+            });
+        }
+    }
+
+    Ok(DiveLog {
+        number: dive_index + 1,
+        datetime,
+        start_time: None,
+        timezone: None,
+        computer: None,
+        duration_seconds: samples.len() as u32 * sample_interval,
+        max_depth_m,
+        min_temp_c,
+        max_temp_c,
+        dive_mode,
+        gas_mixes,
+        tanks,
+        apneas: session_apneas(dive_mode, &samples),
+        samples,
+        site: None,
+        country: None,
+        buddy: None,
+        notes: None,
+        details: DiveDetails::default(),
+        source: None,
+    })
+}
+
 /// Parse a dive from ECOP protocol data (header + profile).
+/// GENIUS-family models only; see `parse_dive` for model dispatch.
 ///
 /// GENIUS header layout (200 bytes, from libdivecomputer mares_iconhd_parser.c):
 ///   0x00: type (u16 LE) - must be 1
@@ -64,8 +330,8 @@ pub fn dive_number_from_header(header: &[u8]) -> u32 {
 ///   0x0C: settings (u32 LE)
 ///   0x20: nsamples (u16 LE)
 ///   0x22: maxdepth (u16 LE, 1/10 m)
-///   0x26: temperature_max (u16 LE, 1/10 C)
-///   0x28: temperature_min (u16 LE, 1/10 C)
+///   0x26: temperature_max (i16 LE, 1/10 C)
+///   0x28: temperature_min (i16 LE, 1/10 C)
 ///   0x3E: atmospheric pressure (u16 LE, 1/1000 bar)
 ///   0x54: gas mixes / tanks (5 entries, 20 bytes each)
 pub fn parse_dive_ecop(dive_index: u32, header: &[u8], profile: &[u8]) -> Result<DiveLog> {
@@ -100,13 +366,19 @@ pub fn parse_dive_ecop(dive_index: u32, header: &[u8], profile: &[u8]) -> Result
     let max_depth_raw = read_u16_le(header, 0x22);
     let max_depth_m = max_depth_raw as f64 / 10.0;
 
-    // Duration: GENIUS uses fixed 5-second sample interval
+    // Water temperature range at 0x26 (max) / 0x28 (min), signed 1/10 deg C
+    let max_temp_c = decode_temperature(read_u16_le(header, 0x26));
+    let min_temp_c = decode_temperature(read_u16_le(header, 0x28));
+
+    // GENIUS models use a fixed 5-second sample interval (see FINDINGS.md for
+    // freedive mode)
     let sample_interval = 5u32;
-    let duration_seconds = nsamples * sample_interval - surftime_min * 60;
+    let duration_seconds = (nsamples * sample_interval).saturating_sub(surftime_min * 60);
 
-    // Gas mixes at 0x54 (5 entries, 20 bytes each)
+    // Gas mixes / tanks at 0x54 (5 entries, 20 bytes each)
     let mut gas_mixes = Vec::new();
-    for i in 0..5 {
+    let mut tanks = Vec::new();
+    for i in 0..MAX_TANKS {
         let gas_offset = 0x54 + i * 20;
         if gas_offset + 4 > header.len() {
             break;
@@ -115,8 +387,27 @@ pub fn parse_dive_ecop(dive_index: u32, header: &[u8], profile: &[u8]) -> Result
         let o2 = (gas_params & 0x7F) as u8;
         let state = ((gas_params >> 21) & 0x03) as u8;
         // state: 0=OFF, 1=READY, 2=INUSE, 3=IGNORED
-        if state > 0 && state < 3 && o2 > 0 && o2 <= 100 {
+        let gas_mix = if state > 0 && state < 3 && o2 > 0 && o2 <= 100 {
             gas_mixes.push(GasMix { o2 });
+            Some(gas_mixes.len() - 1)
+        } else {
+            None
+        };
+
+        // Begin / end pressure at +4 / +6 (u16 LE, 1/100 bar), zero without a transmitter
+        if gas_offset + 8 > header.len() {
+            continue;
+        }
+        let begin = read_u16_le(header, gas_offset + 4);
+        let end = read_u16_le(header, gas_offset + 6);
+        if begin > 0 || end > 0 {
+            tanks.push(Tank {
+                index: i as u8,
+                gas_mix,
+                begin_pressure_bar: (begin > 0).then_some(begin as f64 / 100.0),
+                end_pressure_bar: (end > 0).then_some(end as f64 / 100.0),
+                readings: Vec::new(),
+            });
         }
     }
     if gas_mixes.is_empty() {
@@ -124,19 +415,66 @@ pub fn parse_dive_ecop(dive_index: u32, header: &[u8], profile: &[u8]) -> Result
     }
 
     // Parse DPRS samples from profile data
-    let samples = parse_ecop_profile(profile, sample_interval);
+    let zero_temp = zero_temp_is_reading(min_temp_c, max_temp_c);
+    let parsed = parse_ecop_profile(profile, sample_interval, zero_temp);
+
+    let number = if dive_number > 0 { dive_number } else { dive_index + 1 };
+    if parsed.unknown_tank_readings > 0 {
+        eprintln!(
+            "Warning: dive #{}: skipped {} tank pressure reading(s) with an unknown tank index",
+            number, parsed.unknown_tank_readings
+        );
+    }
+
+    // Attach AIRS readings, adding transmitters without header pressures
+    for (index, readings) in parsed.readings {
+        match tanks.iter_mut().find(|t| t.index == index) {
+            Some(tank) => tank.readings = readings,
+            None => tanks.push(Tank {
+                index,
+                gas_mix: None,
+                begin_pressure_bar: None,
+                end_pressure_bar: None,
+                readings,
+            }),
+        }
+    }
+    tanks.sort_by_key(|t| t.index);
+
+    // Seconds-resolution start time, only when DSTR and DEND agree
+    let start_time = if RECOVER_START_SECONDS {
+        recovered_start(
+            datetime,
+            parsed.start_timestamp,
+            parsed.end_timestamp,
+            (nsamples * sample_interval) as i64,
+            sample_interval,
+        )
+    } else {
+        None
+    };
 
     Ok(DiveLog {
-        number: if dive_number > 0 { dive_number } else { dive_index + 1 },
+        number,
         datetime,
+        start_time,
+        timezone: None,
+        computer: None,
         duration_seconds,
         max_depth_m,
+        min_temp_c,
+        max_temp_c,
         dive_mode,
         gas_mixes,
-        samples,
+        tanks,
+        apneas: session_apneas(dive_mode, &parsed.samples),
+        samples: parsed.samples,
         site: None,
         country: None,
         buddy: None,
+        notes: None,
+        details: DiveDetails::default(),
+        source: None,
     })
 }
 
@@ -147,6 +485,66 @@ const RECORD_DPRS: usize = 34;
 const RECORD_AIRS: usize = 16;
 const RECORD_DEND: usize = 162;
 
+/// Number of gas mix / tank entries in the GENIUS header.
+const MAX_TANKS: usize = 5;
+
+/// Whether start seconds are read from DSTR / DEND. The timestamp offset has
+/// not been checked against a real dump, so this is off unless built with
+/// `--features start-seconds`. See FINDINGS.md.
+const RECOVER_START_SECONDS: bool = cfg!(feature = "start-seconds");
+
+/// Offset of the candidate u32 LE Unix timestamp in DSTR and DEND records
+/// (right after the tag). Unverified, see FINDINGS.md.
+const RECORD_TIMESTAMP_OFFSET: usize = 4;
+
+/// The candidate timestamp of a DSTR/DEND record.
+fn record_timestamp(record: &[u8]) -> Option<i64> {
+    (record.len() >= RECORD_TIMESTAMP_OFFSET + 4)
+        .then(|| read_u32_le(record, RECORD_TIMESTAMP_OFFSET) as i64)
+}
+
+/// Largest UTC offset in use (UTC+14), in seconds.
+const MAX_ZONE_OFFSET_S: i64 = 14 * 3600;
+
+/// Seconds-resolution start time from the DSTR / DEND candidates.
+///
+/// The header minute is on the device's local clock while the candidates are
+/// Unix time, and the zone is unknown. Zone offsets are whole quarter hours,
+/// so a genuine start lies a whole number of quarter hours (at most 14 h)
+/// plus 0-59 s away from the header minute; those seconds are added to the
+/// local header time. The record layouts are not documented, so a start is
+/// only trusted when DEND agrees: as far after DSTR as the samples span, to
+/// within one sample interval.
+fn recovered_start(
+    datetime: NaiveDateTime,
+    start_ts: Option<i64>,
+    end_ts: Option<i64>,
+    recorded_s: i64,
+    sample_interval: u32,
+) -> Option<NaiveDateTime> {
+    let (start, end) = (start_ts?, end_ts?);
+    if (end - start - recorded_s).abs() > sample_interval as i64 {
+        return None;
+    }
+    let diff = start - datetime.and_utc().timestamp();
+    let seconds = diff.rem_euclid(900);
+    (seconds < 60 && diff.abs() < MAX_ZONE_OFFSET_S + 60)
+        .then(|| datetime + chrono::Duration::seconds(seconds))
+}
+
+/// Data extracted from an ECOP profile.
+struct Profile {
+    samples: Vec<Sample>,
+    /// AIRS pressure readings per tank index.
+    readings: BTreeMap<u8, Vec<PressureReading>>,
+    /// AIRS records skipped for a tank index outside the header's slots.
+    unknown_tank_readings: usize,
+    /// Candidate start timestamp of the first DSTR record.
+    start_timestamp: Option<i64>,
+    /// Candidate end timestamp of the DEND record.
+    end_timestamp: Option<i64>,
+}
+
 /// Parse DPRS (depth/pressure) and AIRS samples from ECOP profile data.
 ///
 /// Profile structure:
@@ -159,11 +557,24 @@ const RECORD_DEND: usize = 162;
 ///
 /// Each record: [4-byte tag] [payload] [2-byte CRC] [4-byte tag repeated]
 /// DPRS payload (bytes 4-27): depth(2) + ?(2) + temp(2) + ...
-/// AIRS payload (bytes 4-9): pressure(2) + ...
-fn parse_ecop_profile(profile: &[u8], sample_interval: u32) -> Vec<Sample> {
+/// AIRS payload (bytes 4-9): pressure(2) + tank index(1) + ...
+///
+/// AIRS readings are kept as their own per-tank series, stamped with the time
+/// of the preceding DPRS sample, rather than copied onto every sample.
+///
+/// The candidate timestamps of DSTR and DEND are returned unchecked; see
+/// `recovered_start`.
+fn parse_ecop_profile(
+    profile: &[u8],
+    sample_interval: u32,
+    zero_temp_is_reading: bool,
+) -> Profile {
     let mut samples = Vec::new();
     let mut time_s = 0u32;
-    let mut last_pressure_bar: Option<f64> = None;
+    let mut readings: BTreeMap<u8, Vec<PressureReading>> = BTreeMap::new();
+    let mut unknown_tank_readings = 0;
+    let mut start_timestamp = None;
+    let mut end_timestamp = None;
 
     // Skip the 4-byte SObjectClassifier at the start
     let mut offset = if profile.len() >= 8 && &profile[4..8] == b"DSTR" {
@@ -177,6 +588,11 @@ fn parse_ecop_profile(profile: &[u8], sample_interval: u32) -> Vec<Sample> {
 
         match tag {
             b"DSTR" => {
+                // Only the first DSTR marks the dive start
+                if samples.is_empty() && offset + RECORD_DSTR <= profile.len() {
+                    start_timestamp = start_timestamp
+                        .or_else(|| record_timestamp(&profile[offset..offset + RECORD_DSTR]));
+                }
                 offset += RECORD_DSTR;
             }
             b"TISS" => {
@@ -191,19 +607,15 @@ fn parse_ecop_profile(profile: &[u8], sample_interval: u32) -> Vec<Sample> {
                 let depth_raw = read_u16_le(profile, offset + 4);
                 let depth_m = depth_raw as f64 / 10.0;
 
-                // Temperature at bytes 8-9 (offset+4+4), LE u16, 1/10 deg C
-                let temp_raw = read_u16_le(profile, offset + 8) as i16;
-                let temp_c = if temp_raw > 0 {
-                    Some(temp_raw as f64 / 10.0)
-                } else {
-                    None
-                };
+                // Temperature at bytes 8-9 (offset+4+4), LE i16, 1/10 deg C
+                let temp_raw = read_u16_le(profile, offset + 8);
+                let temp_c = decode_sample_temperature(temp_raw, zero_temp_is_reading);
 
                 samples.push(Sample {
                     time_s,
                     depth_m,
                     temp_c,
-                    pressure_bar: last_pressure_bar,
+                    pressure_bar: None,
                 });
 
                 time_s += sample_interval;
@@ -216,13 +628,26 @@ fn parse_ecop_profile(profile: &[u8], sample_interval: u32) -> Vec<Sample> {
 
                 // Pressure at bytes 4-5, LE u16, 1/100 bar
                 let pressure_raw = read_u16_le(profile, offset + 4);
-                if pressure_raw > 0 {
-                    last_pressure_bar = Some(pressure_raw as f64 / 100.0);
+                // Tank / transmitter slot at byte 6 (unverified, see FINDINGS.md);
+                // readings for slots the header cannot have are dropped rather
+                // than merged into another tank's series
+                let tank = profile[offset + 6];
+                if tank as usize >= MAX_TANKS {
+                    unknown_tank_readings += 1;
+                } else if pressure_raw > 0 {
+                    // Stamp with the time of the sample this record followed
+                    readings.entry(tank).or_default().push(PressureReading {
+                        time_s: samples.last().map_or(0, |s| s.time_s),
+                        pressure_bar: pressure_raw as f64 / 100.0,
+                    });
                 }
 
                 offset += RECORD_AIRS;
             }
             b"DEND" => {
+                if offset + RECORD_DEND <= profile.len() {
+                    end_timestamp = record_timestamp(&profile[offset..offset + RECORD_DEND]);
+                }
                 offset += RECORD_DEND;
             }
             _ => {
@@ -232,25 +657,383 @@ fn parse_ecop_profile(profile: &[u8], sample_interval: u32) -> Vec<Sample> {
         }
     }
 
-    samples
+    Profile {
+        samples,
+        readings,
+        unknown_tank_readings,
+        start_timestamp,
+        end_timestamp,
+    }
 }
 
 /// Export a dive as CSV.
-pub fn dive_to_csv(dive: &DiveLog) -> String {
-    let mut csv = String::from("time_s,depth_m,temp_c,pressure_bar\n");
-    for s in &dive.samples {
+///
+/// Tank pressures are mapped onto the sample rows according to `fill`.
+/// Single-tank dives keep one `pressure_bar` column; dives with several
+/// transmitters get a `pressure_tN_bar` column per tank instead.
+pub fn dive_to_csv(dive: &DiveLog, fill: PressureFill) -> String {
+    let pressures = dive.sample_pressures(fill);
+
+    let mut csv = String::from("time_s,depth_m,temp_c");
+    if pressures.len() > 1 {
+        for (t, _) in &pressures {
+            csv.push_str(&format!(",pressure_t{}_bar", t + 1));
+        }
+    } else {
+        csv.push_str(",pressure_bar");
+    }
+    csv.push('\n');
+
+    for (i, s) in dive.samples.iter().enumerate() {
         csv.push_str(&format!(
-            "{},{:.1},{},{}",
+            "{},{:.1},{}",
             s.time_s,
             s.depth_m,
             s.temp_c
                 .map(|t| format!("{t:.1}"))
                 .unwrap_or_default(),
-            s.pressure_bar
-                .map(|p| format!("{p:.1}"))
-                .unwrap_or_default(),
         ));
+        if pressures.is_empty() {
+            csv.push(',');
+        }
+        for (_, values) in &pressures {
+            csv.push(',');
+            if let Some(p) = values[i] {
+                csv.push_str(&format!("{p:.1}"));
+            }
+        }
         csv.push('\n');
     }
     csv
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A tagged record of `size` bytes with `fields` written at their offsets.
+    fn record(tag: &[u8; 4], size: usize, fields: &[(usize, &[u8])]) -> Vec<u8> {
+        let mut data = vec![0u8; size];
+        data[..4].copy_from_slice(tag);
+        data[size - 4..].copy_from_slice(tag);
+        for (offset, bytes) in fields {
+            data[*offset..offset + bytes.len()].copy_from_slice(bytes);
+        }
+        data
+    }
+
+    fn dprs(depth_dm: u16, temp_raw: u16) -> Vec<u8> {
+        record(
+            b"DPRS",
+            RECORD_DPRS,
+            &[(4, &depth_dm.to_le_bytes()), (8, &temp_raw.to_le_bytes())],
+        )
+    }
+
+    fn airs(pressure_cbar: u16, tank: u8) -> Vec<u8> {
+        record(
+            b"AIRS",
+            RECORD_AIRS,
+            &[(4, &pressure_cbar.to_le_bytes()), (6, &[tank])],
+        )
+    }
+
+    /// GENIUS packed datetime.
+    fn packed(year: u32, month: u32, day: u32, hour: u32, minute: u32) -> u32 {
+        (year << 20) | (month << 16) | (day << 11) | (minute << 5) | hour
+    }
+
+    /// 200-byte GENIUS header for an air dive with `nsamples` samples.
+    fn genius_header(nsamples: u16, max_temp: i16, min_temp: i16) -> Vec<u8> {
+        let mut header = vec![0u8; 200];
+        header[0x04..0x08].copy_from_slice(&7u32.to_le_bytes());
+        header[0x08..0x0C].copy_from_slice(&packed(2025, 3, 4, 10, 15).to_le_bytes());
+        header[0x20..0x22].copy_from_slice(&nsamples.to_le_bytes());
+        header[0x22..0x24].copy_from_slice(&123u16.to_le_bytes());
+        header[0x26..0x28].copy_from_slice(&max_temp.to_le_bytes());
+        header[0x28..0x2A].copy_from_slice(&min_temp.to_le_bytes());
+        header
+    }
+
+    /// Local header minute used by `genius_header`.
+    fn header_time() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 3, 4).unwrap().and_hms_opt(10, 15, 0).unwrap()
+    }
+
+    /// `header_time` read as UTC.
+    fn header_ts() -> i64 {
+        header_time().and_utc().timestamp()
+    }
+
+    fn profile(records: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = vec![0u8; 4];
+        for r in records {
+            data.extend_from_slice(r);
+        }
+        data
+    }
+
+    fn timed_profile(dstr: &[(usize, &[u8])], dend: &[(usize, &[u8])]) -> Vec<u8> {
+        profile(&[
+            record(b"DSTR", RECORD_DSTR, dstr),
+            dprs(50, 200),
+            dprs(100, 200),
+            dprs(50, 200),
+            dprs(0, 200),
+            record(b"DEND", RECORD_DEND, dend),
+        ])
+    }
+
+    /// Raw iconhd-family dump `[length][samples][header]` for `model`, with
+    /// `(depth dm, temp dC, pressure cbar)` samples.
+    fn iconhd_dump(model: Model, mode: u16, o2: u8, samples: &[(u16, i16, u16)]) -> Vec<u8> {
+        let layout = iconhd_layout(model);
+        let mut header = vec![0u8; layout.header_size];
+        header[0x00..0x02].copy_from_slice(&mode.to_le_bytes());
+        header[0x02..0x04].copy_from_slice(&(samples.len() as u16).to_le_bytes());
+        header[0x04..0x06].copy_from_slice(&123u16.to_le_bytes());
+        // 14:07 on 2019-08-21
+        for (i, v) in [14u16, 7, 21, 7, 119].iter().enumerate() {
+            header[0x06 + i * 2..0x08 + i * 2].copy_from_slice(&v.to_le_bytes());
+        }
+        header[0x10] = o2;
+        header[0x42..0x44].copy_from_slice(&(-5i16).to_le_bytes());
+        header[0x44..0x46].copy_from_slice(&245i16.to_le_bytes());
+        if let Some(tanks) = layout.tanks {
+            header[tanks..tanks + 2].copy_from_slice(&20000u16.to_le_bytes());
+            header[tanks + 2..tanks + 4].copy_from_slice(&6000u16.to_le_bytes());
+        }
+
+        let mut data = vec![0u8; 4];
+        for &(depth, temp, pressure) in samples {
+            let mut sample = vec![0u8; layout.sample_size];
+            sample[0..2].copy_from_slice(&depth.to_le_bytes());
+            sample[2..4].copy_from_slice(&temp.to_le_bytes());
+            if layout.sample_size >= 12 {
+                sample[8..10].copy_from_slice(&pressure.to_le_bytes());
+            }
+            data.extend_from_slice(&sample);
+        }
+        data.extend_from_slice(&header);
+        let length = data.len() as u32;
+        data[0..4].copy_from_slice(&length.to_le_bytes());
+        data
+    }
+
+    fn parse_dump(model: Model, data: &[u8]) -> DiveLog {
+        let (header, profile) = split_iconhd_dive(model, data).unwrap();
+        parse_dive(model, 0, &header, &profile).unwrap()
+    }
+
+    /// `(time_s, depth_m, temp_c)` of every sample.
+    fn points(dive: &DiveLog) -> Vec<(u32, f64, Option<f64>)> {
+        dive.samples.iter().map(|s| (s.time_s, s.depth_m, s.temp_c)).collect()
+    }
+
+    #[test]
+    fn icon_hd_air_dive() {
+        let data = iconhd_dump(Model::IconHD, 0, 0, &[(30, 245, 0), (100, 240, 0), (50, -15, 0)]);
+        let dive = parse_dump(Model::IconHD, &data);
+        assert_eq!(dive.dive_mode, DiveMode::Air);
+        assert_eq!(dive.datetime.to_string(), "2019-08-21 14:07:00");
+        assert_eq!(
+            points(&dive),
+            [(0, 3.0, Some(24.5)), (5, 10.0, Some(24.0)), (10, 5.0, Some(-1.5))]
+        );
+        assert_eq!(dive.duration_seconds, 15);
+        assert_eq!(dive.max_depth_m, 12.3);
+        assert_eq!((dive.min_temp_c, dive.max_temp_c), (Some(-0.5), Some(24.5)));
+        assert!(dive.tanks.is_empty());
+    }
+
+    #[test]
+    fn puck_pro_freedive_uses_one_second_samples() {
+        let samples = [(0, 200, 0), (45, 199, 0), (90, 198, 0), (20, 199, 0)];
+        let data = iconhd_dump(Model::PuckPro, 3, 0, &samples);
+        let dive = parse_dump(Model::PuckPro, &data);
+        assert_eq!(dive.dive_mode, DiveMode::Freedive);
+        assert_eq!(
+            points(&dive),
+            [
+                (0, 0.0, Some(20.0)),
+                (1, 4.5, Some(19.9)),
+                (2, 9.0, Some(19.8)),
+                (3, 2.0, Some(19.9))
+            ]
+        );
+        assert_eq!(dive.duration_seconds, 4);
+    }
+
+    #[test]
+    fn nemo_wide_2_nitrox_dive() {
+        let data = iconhd_dump(Model::NemoWide2, 2, 32, &[(20, 180, 0), (40, 175, 0)]);
+        let dive = parse_dump(Model::NemoWide2, &data);
+        assert_eq!(dive.dive_mode, DiveMode::Nitrox);
+        assert_eq!(dive.gas_mixes.iter().map(|g| g.o2).collect::<Vec<_>>(), [32]);
+        assert_eq!(points(&dive), [(0, 2.0, Some(18.0)), (5, 4.0, Some(17.5))]);
+    }
+
+    #[test]
+    fn twelve_byte_samples_carry_tank_pressure() {
+        for model in [Model::IconAir, Model::QuadAir] {
+            let samples = [(30, 250, 19900), (80, 245, 0), (60, 240, 19500)];
+            let data = iconhd_dump(model, 0, 0, &samples);
+            let dive = parse_dump(model, &data);
+            assert_eq!(
+                points(&dive),
+                [(0, 3.0, Some(25.0)), (5, 8.0, Some(24.5)), (10, 6.0, Some(24.0))]
+            );
+            let tank = &dive.tanks[0];
+            assert_eq!(tank.begin_pressure_bar, Some(200.0));
+            assert_eq!(tank.end_pressure_bar, Some(60.0));
+            let readings: Vec<(u32, f64)> =
+                tank.readings.iter().map(|r| (r.time_s, r.pressure_bar)).collect();
+            assert_eq!(readings, [(0, 199.0), (10, 195.0)]);
+        }
+    }
+
+    #[test]
+    fn truncated_iconhd_dump_is_an_error() {
+        let mut data = iconhd_dump(Model::IconHD, 0, 0, &[(30, 245, 0), (100, 240, 0)]);
+        data.truncate(data.len() - 10);
+        assert!(split_iconhd_dive(Model::IconHD, &data).is_err());
+
+        // Length smaller than the header
+        let mut data = iconhd_dump(Model::IconHD, 0, 0, &[(30, 245, 0)]);
+        data[0..4].copy_from_slice(&16u32.to_le_bytes());
+        assert!(split_iconhd_dive(Model::IconHD, &data).is_err());
+    }
+
+    #[test]
+    fn decodes_signed_temperatures() {
+        assert_eq!(decode_temperature((-15i16) as u16), Some(-1.5));
+        assert_eq!(decode_temperature(0), Some(0.0));
+        assert_eq!(decode_temperature(285), Some(28.5));
+        assert_eq!(decode_temperature(i16::MIN as u16), None);
+        assert_eq!(decode_temperature(i16::MAX as u16), None);
+        // Outside the -10..=50 deg C a sensor can report
+        assert_eq!(decode_temperature((-101i16) as u16), None);
+        assert_eq!(decode_temperature(501), None);
+    }
+
+    #[test]
+    fn ice_dive_sample_temperatures() {
+        let temps: [i16; 7] = [-15, -1, 0, i16::MIN, i16::MAX, -200, 600];
+        let records: Vec<Vec<u8>> = temps.iter().map(|&t| dprs(50, t as u16)).collect();
+        let dive = parse_dive_ecop(0, &genius_header(7, 0, -15), &profile(&records)).unwrap();
+        let decoded: Vec<Option<f64>> = dive.samples.iter().map(|s| s.temp_c).collect();
+        // The header range reaches 0 deg C, so raw 0 is a reading
+        assert_eq!(decoded, [Some(-1.5), Some(-0.1), Some(0.0), None, None, None, None]);
+    }
+
+    #[test]
+    fn zero_sample_temperature_without_cold_water_is_missing() {
+        let records = [dprs(50, 245), dprs(50, 0)];
+        let decoded = |header: Vec<u8>| -> Vec<Option<f64>> {
+            let dive = parse_dive_ecop(0, &header, &profile(&records)).unwrap();
+            dive.samples.iter().map(|s| s.temp_c).collect()
+        };
+        // Warm water: a 0 sample cannot be real
+        assert_eq!(decoded(genius_header(2, 255, 245)), [Some(24.5), None]);
+        // No sensor data in the header
+        assert_eq!(decoded(genius_header(2, 0, 0)), [Some(24.5), None]);
+        assert_eq!(decoded(genius_header(2, i16::MAX, i16::MIN)), [Some(24.5), None]);
+    }
+
+    #[test]
+    fn ice_dive_header_temperatures() {
+        let dive = parse_dive_ecop(0, &genius_header(0, 0, -15), &[]).unwrap();
+        assert_eq!(dive.max_temp_c, Some(0.0));
+        assert_eq!(dive.min_temp_c, Some(-1.5));
+
+        let dive = parse_dive_ecop(0, &genius_header(0, i16::MAX, i16::MIN), &[]).unwrap();
+        assert_eq!(dive.max_temp_c, None);
+        assert_eq!(dive.min_temp_c, None);
+
+        let dive = parse_dive_ecop(0, &genius_header(0, 700, -300), &[]).unwrap();
+        assert_eq!(dive.max_temp_c, None);
+        assert_eq!(dive.min_temp_c, None);
+    }
+
+    #[test]
+    fn airs_readings_per_tank_skip_unknown_slots() {
+        let data = profile(&[
+            dprs(50, 200),
+            airs(20000, 0),
+            airs(19000, 1),
+            dprs(100, 200),
+            airs(18000, 7),
+            airs(19500, 0),
+        ]);
+        let dive = parse_dive_ecop(0, &genius_header(2, 200, 200), &data).unwrap();
+        let series = dive.pressure_series();
+        let bars: Vec<(u8, Vec<(u32, f64)>)> = series
+            .iter()
+            .map(|(t, r)| (*t, r.iter().map(|r| (r.time_s, r.pressure_bar)).collect()))
+            .collect();
+        assert_eq!(bars, [(0, vec![(0, 200.0), (5, 195.0)]), (1, vec![(0, 190.0)])]);
+    }
+
+    #[test]
+    fn dstr_and_dend_candidates_are_read_at_offset_4() {
+        let (start, end) = (1_741_083_337u32, 1_741_083_357u32);
+        let data = timed_profile(&[(4, &start.to_le_bytes())], &[(4, &end.to_le_bytes())]);
+        let parsed = parse_ecop_profile(&data, 5, false);
+        assert_eq!(parsed.start_timestamp, Some(start as i64));
+        assert_eq!(parsed.end_timestamp, Some(end as i64));
+
+        // Plausible timestamps at other offsets are not picked up
+        let decoy = timed_profile(
+            &[(4, &1234u32.to_le_bytes()), (20, &start.to_le_bytes())],
+            &[(30, &end.to_le_bytes())],
+        );
+        let parsed = parse_ecop_profile(&decoy, 5, false);
+        assert_eq!(parsed.start_timestamp, Some(1234));
+        assert_eq!(parsed.end_timestamp, Some(0));
+    }
+
+    #[test]
+    fn start_seconds_are_found_whatever_the_zone() {
+        // Device clock at UTC, UTC-7, UTC+5:45, UTC+14 and UTC-12
+        for zone_s in [0, -7 * 3600, 5 * 3600 + 45 * 60, 14 * 3600, -12 * 3600] {
+            let start = header_ts() - zone_s + 37;
+            let found = recovered_start(header_time(), Some(start), Some(start + 20), 20, 5);
+            assert_eq!(
+                found.map(|t| t.format("%H:%M:%S").to_string()).as_deref(),
+                Some("10:15:37"),
+                "zone {zone_s}"
+            );
+        }
+    }
+
+    #[test]
+    fn implausible_start_candidates_are_rejected() {
+        let at = |start: i64, end: Option<i64>| {
+            recovered_start(header_time(), Some(start), end, 20, 5)
+        };
+        let start = header_ts() + 37;
+        // Not within a minute of a quarter-hour zone offset
+        assert_eq!(at(header_ts() + 120, Some(header_ts() + 140)), None);
+        // Further away than any zone
+        assert_eq!(at(start + 15 * 3600, Some(start + 15 * 3600 + 20)), None);
+        // DEND missing or disagreeing with the sample span
+        assert_eq!(at(start, None), None);
+        assert_eq!(at(start, Some(start + 40)), None);
+        assert_eq!(recovered_start(header_time(), None, Some(start + 20), 20, 5), None);
+    }
+
+    #[test]
+    fn start_seconds_only_with_the_feature() {
+        let start = (header_ts() + 37) as u32;
+        let end = start + 4 * 5;
+        let data = timed_profile(&[(4, &start.to_le_bytes())], &[(4, &end.to_le_bytes())]);
+        let dive = parse_dive_ecop(0, &genius_header(4, 200, 200), &data).unwrap();
+        if RECOVER_START_SECONDS {
+            assert_eq!(dive.start_time.unwrap().format("%H:%M:%S").to_string(), "10:15:37");
+            assert_eq!(dive.start().1, TimePrecision::Second);
+        } else {
+            assert_eq!(dive.start_time, None);
+            assert_eq!(dive.start().1, TimePrecision::Minute);
+        }
+    }
+}
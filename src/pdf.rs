@@ -0,0 +1,437 @@
+use crate::types::{DiveLog, DiveMode};
+
+/// A4 portrait in points.
+const PAGE_W: f64 = 595.0;
+const PAGE_H: f64 = 842.0;
+const MARGIN: f64 = 40.0;
+
+/// Helvetica advance widths (1/1000 em) for ASCII 32..=126, from the
+/// standard AFM metrics. Used to wrap and right-align text.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// Width of `text` in points at `size`, in regular Helvetica (bold runs a
+/// little wider, which the layout leaves room for).
+fn text_width(text: &str, size: f64) -> f64 {
+    let units: u32 = text
+        .chars()
+        .map(|c| match c as u32 {
+            code @ 32..=126 => HELVETICA_WIDTHS[(code - 32) as usize] as u32,
+            _ => 556,
+        })
+        .sum();
+    units as f64 * size / 1000.0
+}
+
+/// Encode text as a PDF literal string in WinAnsiEncoding. Characters
+/// outside it become '?'.
+fn pdf_string(text: &str) -> String {
+    let mut out = String::from("(");
+    for c in text.chars() {
+        let code = match c {
+            '€' => 0x80,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            c if (c as u32) < 0x80 || (0xA0..=0xFF).contains(&(c as u32)) => c as u32,
+            _ => '?' as u32,
+        };
+        match code {
+            0x28 | 0x29 | 0x5C => {
+                out.push('\\');
+                out.push(code as u8 as char);
+            }
+            0x20..=0x7E => out.push(code as u8 as char),
+            _ => out.push_str(&format!("\\{:03o}", code)),
+        }
+    }
+    out.push(')');
+    out
+}
+
+/// Split text into lines that fit `width` at `size`, breaking at spaces.
+fn wrap(text: &str, width: f64, size: f64) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if text_width(&candidate, size) > width && !line.is_empty() {
+                lines.push(std::mem::replace(&mut line, word.to_string()));
+            } else {
+                line = candidate;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Drawing operations of one page. Coordinates are measured from the top
+/// left corner and flipped to PDF user space on output.
+struct Page {
+    ops: String,
+}
+
+impl Page {
+    fn new() -> Self {
+        Page { ops: String::new() }
+    }
+
+    fn text(&mut self, x: f64, y: f64, size: f64, bold: bool, text: &str) {
+        let font = if bold { "F2" } else { "F1" };
+        self.ops.push_str(&format!(
+            "BT /{} {:.1} Tf {:.2} {:.2} Td {} Tj ET\n",
+            font,
+            size,
+            x,
+            PAGE_H - y,
+            pdf_string(text)
+        ));
+    }
+
+    fn text_right(&mut self, x: f64, y: f64, size: f64, bold: bool, text: &str) {
+        self.text(x - text_width(text, size), y, size, bold, text);
+    }
+
+    fn text_center(&mut self, x: f64, y: f64, size: f64, text: &str) {
+        self.text(x - text_width(text, size) / 2.0, y, size, false, text);
+    }
+
+    fn stroke_color(&mut self, rgb: (f64, f64, f64)) {
+        self.ops.push_str(&format!("{:.2} {:.2} {:.2} RG\n", rgb.0, rgb.1, rgb.2));
+    }
+
+    fn fill_color(&mut self, rgb: (f64, f64, f64)) {
+        self.ops.push_str(&format!("{:.2} {:.2} {:.2} rg\n", rgb.0, rgb.1, rgb.2));
+    }
+
+    fn line_width(&mut self, width: f64) {
+        self.ops.push_str(&format!("{:.2} w\n", width));
+    }
+
+    fn line(&mut self, x1: f64, y1: f64, x2: f64, y2: f64) {
+        self.ops.push_str(&format!(
+            "{:.2} {:.2} m {:.2} {:.2} l S\n",
+            x1,
+            PAGE_H - y1,
+            x2,
+            PAGE_H - y2
+        ));
+    }
+
+    fn rect(&mut self, x: f64, y: f64, w: f64, h: f64) {
+        self.ops
+            .push_str(&format!("{:.2} {:.2} {:.2} {:.2} re S\n", x, PAGE_H - y - h, w, h));
+    }
+
+    /// Polyline through `points`; closed and filled instead when `fill`.
+    fn path(&mut self, points: &[(f64, f64)], fill: bool) {
+        for (i, (x, y)) in points.iter().enumerate() {
+            let op = if i == 0 { "m" } else { "l" };
+            self.ops.push_str(&format!("{:.2} {:.2} {}\n", x, PAGE_H - y, op));
+        }
+        self.ops.push_str(if fill { "f\n" } else { "S\n" });
+    }
+}
+
+/// Assemble pages into a PDF file with the two standard Helvetica fonts.
+fn build_document(pages: &[Page]) -> Vec<u8> {
+    // Objects: 1 catalog, 2 page tree, 3-4 fonts, 5 info, then page and
+    // content stream per page
+    let mut objects: Vec<String> = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        String::new(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+        format!(
+            "<< /Producer {} /CreationDate (D:{}Z) >>",
+            pdf_string(concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"))),
+            chrono::Utc::now().format("%Y%m%d%H%M%S")
+        ),
+    ];
+    let mut kids = Vec::new();
+    for page in pages {
+        let page_id = objects.len() + 1;
+        kids.push(format!("{} 0 R", page_id));
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+            PAGE_W,
+            PAGE_H,
+            page_id + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            page.ops.len(),
+            page.ops
+        ));
+    }
+    objects[1] = format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        kids.join(" "),
+        pages.len()
+    );
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+    let xref = out.len();
+    out.push_str(&format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1));
+    for offset in offsets {
+        out.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    out.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R /Info 5 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    ));
+    out.into_bytes()
+}
+
+/// Smallest step from `steps` giving at most `max_ticks` intervals over `range`.
+fn tick_step(range: f64, steps: &[f64], max_ticks: f64) -> f64 {
+    steps
+        .iter()
+        .copied()
+        .find(|step| range / step <= max_ticks)
+        .unwrap_or(*steps.last().unwrap())
+}
+
+fn mmss(seconds: u32) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Depth profile chart with time and depth grid, in the box at `(x, y)`.
+fn depth_chart(page: &mut Page, dive: &DiveLog, x: f64, y: f64, w: f64, h: f64) {
+    let grey = (0.8, 0.8, 0.8);
+    let black = (0.0, 0.0, 0.0);
+
+    let end_s = dive
+        .samples
+        .last()
+        .map_or(dive.duration_seconds, |s| s.time_s)
+        .max(1) as f64;
+    let depth_step = tick_step(dive.max_depth_m, &[1.0, 2.0, 5.0, 10.0, 20.0], 8.0);
+    let max_depth = ((dive.max_depth_m / depth_step).ceil() * depth_step).max(depth_step);
+    let time_step = tick_step(
+        end_s,
+        &[1.0, 2.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 900.0, 1200.0, 1800.0, 3600.0],
+        10.0,
+    );
+    let px = |t: f64| x + t / end_s * w;
+    let py = |d: f64| y + d / max_depth * h;
+
+    page.line_width(0.5);
+    page.stroke_color(grey);
+    let mut depth = 0.0;
+    while depth <= max_depth + 1e-9 {
+        page.line(x, py(depth), x + w, py(depth));
+        page.text_right(x - 4.0, py(depth) + 3.0, 8.0, false, &format!("{:.0}", depth));
+        depth += depth_step;
+    }
+    let mut time = 0.0;
+    while time <= end_s + 1e-9 {
+        page.line(px(time), y, px(time), y + h);
+        page.text_center(px(time), y + h + 12.0, 8.0, &mmss(time as u32));
+        time += time_step;
+    }
+    page.text(x - 28.0, y - 8.0, 8.0, false, "m");
+    page.text_right(x + w, y + h + 24.0, 8.0, false, "time (min:s)");
+
+    if dive.samples.len() > 1 {
+        let outline: Vec<(f64, f64)> = dive
+            .samples
+            .iter()
+            .map(|s| (px(s.time_s as f64), py(s.depth_m)))
+            .collect();
+        let mut area = outline.clone();
+        area.push((outline.last().unwrap().0, y));
+        area.push((outline[0].0, y));
+        page.fill_color((0.85, 0.92, 1.0));
+        page.path(&area, true);
+        page.line_width(1.2);
+        page.stroke_color((0.1, 0.35, 0.75));
+        page.path(&outline, false);
+    } else {
+        page.fill_color(black);
+        page.text_center(x + w / 2.0, y + h / 2.0, 10.0, "No profile samples");
+    }
+
+    page.fill_color(black);
+    page.stroke_color(black);
+    page.line_width(0.8);
+    page.rect(x, y, w, h);
+}
+
+/// Header fields of a dive as label / value pairs.
+fn header_fields(dive: &DiveLog) -> Vec<(&'static str, String)> {
+    let (start, _) = dive.start();
+    let time = match dive.utc_offset() {
+        Some(offset) => format!("{} (UTC{})", start.format("%H:%M:%S"), offset),
+        None => start.format("%H:%M:%S").to_string(),
+    };
+    let temps = match dive.temp_range() {
+        Some((min, max)) if (max - min).abs() >= 0.05 => format!("{:.1} – {:.1} °C", min, max),
+        Some((min, _)) => format!("{:.1} °C", min),
+        None => "-".to_string(),
+    };
+    let gas = match dive.dive_mode {
+        DiveMode::Freedive | DiveMode::Gauge => "-".to_string(),
+        _ => dive
+            .gas_mixes
+            .iter()
+            .map(|g| g.name())
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+    let pressures = dive
+        .tank_pressures()
+        .iter()
+        .map(|(tank, start, end)| {
+            format!("{}: {:.0} - {:.0} bar", dive.tank_label(*tank), start, end)
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let computer = dive.computer.as_ref().map(|dc| match dc.serial {
+        Some(ref serial) => format!("{} ({})", dc.model, serial),
+        None => dc.model.clone(),
+    });
+    let text = |value: Option<&str>| value.filter(|v| !v.is_empty()).unwrap_or("-").to_string();
+
+    vec![
+        ("Date", start.format("%Y-%m-%d").to_string()),
+        ("Time", time),
+        ("Duration", mmss(dive.duration_seconds)),
+        ("Max depth", format!("{:.1} m", dive.max_depth_m)),
+        ("Avg depth", dive.avg_depth().map_or("-".to_string(), |d| format!("{:.1} m", d))),
+        ("Water temp", temps),
+        ("Mode", format!("{:?}", dive.dive_mode)),
+        ("Gas", gas),
+        ("Pressure", if pressures.is_empty() { "-".to_string() } else { pressures }),
+        ("Computer", text(computer.as_deref())),
+        ("Site", text(dive.site.as_deref())),
+        ("Country", text(dive.country.as_deref())),
+        ("Buddy", text(dive.buddy.as_deref())),
+    ]
+    .into_iter()
+    .chain(dive.details.fields())
+    .collect()
+}
+
+fn dive_page(dive: &DiveLog, page_no: usize, page_count: usize) -> Page {
+    let mut page = Page::new();
+    let content_w = PAGE_W - 2.0 * MARGIN;
+    let right = PAGE_W - MARGIN;
+
+    // Title
+    page.text(MARGIN, 60.0, 20.0, true, &format!("Dive #{}", dive.number));
+    page.text_right(right, 60.0, 12.0, false, &dive.start().0.format("%A %-d %B %Y").to_string());
+    page.line_width(1.0);
+    page.line(MARGIN, 70.0, right, 70.0);
+
+    // Header fields in two columns
+    let fields = header_fields(dive);
+    let rows = fields.len().div_ceil(2);
+    let col_w = content_w / 2.0;
+    for (i, (label, value)) in fields.iter().enumerate() {
+        let (col, row) = (i / rows, i % rows);
+        let x = MARGIN + col as f64 * col_w;
+        let y = 92.0 + row as f64 * 16.0;
+        page.text(x, y, 9.0, true, label);
+        let value_w = col_w - 80.0;
+        let mut value = value.clone();
+        while text_width(&value, 10.0) > value_w && value.chars().count() > 1 {
+            value.pop();
+            value.pop();
+            value.push('…');
+        }
+        page.text(x + 70.0, y, 10.0, false, &value);
+    }
+
+    // Depth profile
+    let chart_top = 92.0 + rows as f64 * 16.0 + 16.0;
+    page.text(MARGIN, chart_top, 11.0, true, "Depth profile");
+    let chart_y = chart_top + 18.0;
+    let chart_h = 230.0;
+    depth_chart(&mut page, dive, MARGIN + 30.0, chart_y, content_w - 30.0, chart_h);
+
+    // Notes
+    let notes_top = chart_y + chart_h + 44.0;
+    let stamp_top = PAGE_H - MARGIN - 130.0;
+    page.text(MARGIN, notes_top, 11.0, true, "Notes");
+    let notes_h = stamp_top - notes_top - 28.0;
+    page.line_width(0.8);
+    page.rect(MARGIN, notes_top + 8.0, content_w, notes_h);
+    let max_lines = ((notes_h - 8.0) / 13.0).floor() as usize;
+    let lines = dive
+        .notes
+        .as_deref()
+        .map(|n| wrap(n, content_w - 16.0, 10.0))
+        .unwrap_or_default();
+    for (i, line) in lines.iter().take(max_lines).enumerate() {
+        let line = if i + 1 == max_lines && lines.len() > max_lines {
+            format!("{} …", line)
+        } else {
+            line.clone()
+        };
+        page.text(MARGIN + 8.0, notes_top + 24.0 + i as f64 * 13.0, 10.0, false, &line);
+    }
+    if lines.is_empty() {
+        // Ruled lines to write on
+        page.stroke_color((0.85, 0.85, 0.85));
+        let mut y = notes_top + 30.0;
+        while y < notes_top + notes_h {
+            page.line(MARGIN + 8.0, y, right - 8.0, y);
+            y += 20.0;
+        }
+        page.stroke_color((0.0, 0.0, 0.0));
+    }
+
+    // Signature and stamp
+    let box_w = (content_w - 20.0) / 2.0;
+    page.text(MARGIN, stamp_top, 11.0, true, "Verification");
+    page.rect(MARGIN, stamp_top + 8.0, box_w, 110.0);
+    page.rect(MARGIN + box_w + 20.0, stamp_top + 8.0, box_w, 110.0);
+    page.text(MARGIN + 8.0, stamp_top + 24.0, 9.0, false, "Instructor / buddy name");
+    page.line(MARGIN + 8.0, stamp_top + 52.0, MARGIN + box_w - 8.0, stamp_top + 52.0);
+    page.text(MARGIN + 8.0, stamp_top + 68.0, 9.0, false, "Certification no.");
+    page.line(MARGIN + 8.0, stamp_top + 90.0, MARGIN + box_w - 8.0, stamp_top + 90.0);
+    page.text(MARGIN + 8.0, stamp_top + 110.0, 9.0, false, "Signature");
+    page.text(MARGIN + box_w + 28.0, stamp_top + 24.0, 9.0, false, "Dive centre stamp");
+
+    // Footer
+    page.text(MARGIN, PAGE_H - 20.0, 8.0, false, concat!("Generated by ", env!("CARGO_PKG_NAME")));
+    page.text_right(right, PAGE_H - 20.0, 8.0, false, &format!("Page {} of {}", page_no, page_count));
+
+    page
+}
+
+/// Render one logbook page per dive into a single PDF document.
+pub fn dives_to_pdf(dives: &[DiveLog]) -> Vec<u8> {
+    let pages: Vec<Page> = dives
+        .iter()
+        .enumerate()
+        .map(|(i, dive)| dive_page(dive, i + 1, dives.len()))
+        .collect();
+    build_document(&pages)
+}
@@ -0,0 +1,455 @@
+use anyhow::{Context, Result};
+
+use crate::freedive;
+use crate::types::{DiveLog, DiveMode};
+use crate::xml::XmlWriter;
+
+const FONT: &str = "DejaVu Sans, Helvetica, Arial, sans-serif";
+/// Bundled so PNG labels render on machines without system fonts.
+const FALLBACK_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
+const DEPTH_COLOR: &str = "#1f5fbf";
+const DEPTH_FILL: &str = "#dbe9fb";
+const TEMP_COLOR: &str = "#d62728";
+/// Dash pattern of the temperature line, which shares red with fast ascents.
+const TEMP_DASH: &str = "6 4";
+/// Same order as the TUI tank colours.
+const TANK_COLORS: [&str; 4] = ["#2ca02c", "#b0309b", "#c9a100", "#4aa3df"];
+
+/// Ascent rate bands (m/min) and their colours; descents and slow ascents
+/// keep the depth colour.
+const ASCENT_WARN: f64 = 10.0;
+const ASCENT_FAST: f64 = 15.0;
+const ASCENT_WARN_COLOR: &str = "#f39c12";
+const ASCENT_FAST_COLOR: &str = "#e02020";
+
+/// `(time_s, value)` pairs.
+type Points = Vec<(u32, f64)>;
+
+/// What to draw besides the depth profile.
+pub struct PlotOptions {
+    pub width: u32,
+    pub height: u32,
+    pub temp: bool,
+    pub pressure: bool,
+}
+
+/// Step from the 1-2-5 series giving at most `max_ticks` intervals over `range`.
+fn nice_step(range: f64, max_ticks: f64) -> f64 {
+    let raw = (range / max_ticks).max(1e-9);
+    let magnitude = 10f64.powf(raw.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|step| *step >= raw)
+        .unwrap_or(10.0 * magnitude)
+}
+
+/// Time axis step in seconds: whole minutes unless the dive is very short.
+fn time_step(end_s: f64) -> f64 {
+    [1.0, 2.0, 5.0, 10.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 900.0, 1800.0, 3600.0]
+        .into_iter()
+        .find(|step| end_s / step <= 12.0)
+        .unwrap_or(3600.0)
+}
+
+/// Tick values from `lo` to `hi` (inclusive), `step` apart.
+fn ticks(lo: f64, hi: f64, step: f64) -> impl Iterator<Item = f64> {
+    let count = ((hi - lo) / step + 1e-6).floor() as u32;
+    (0..=count).map(move |i| lo + i as f64 * step)
+}
+
+/// Tick label with as many decimals as the step needs.
+fn tick_label(v: f64, step: f64) -> String {
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    format!("{:.*}", decimals, v)
+}
+
+fn num(v: f64) -> String {
+    format!("{:.1}", v)
+}
+
+fn polyline(xml: &mut XmlWriter, points: &[(f64, f64)], color: &str, width: f64, dash: Option<&str>) {
+    let points = points
+        .iter()
+        .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let width = num(width);
+    let mut attrs = vec![
+        ("points", points.as_str()),
+        ("fill", "none"),
+        ("stroke", color),
+        ("stroke-width", &width),
+        ("stroke-linejoin", "round"),
+    ];
+    if let Some(dash) = dash {
+        attrs.push(("stroke-dasharray", dash));
+    }
+    xml.empty("polyline", &attrs);
+}
+
+fn text(xml: &mut XmlWriter, x: f64, y: f64, anchor: &str, size: f64, color: &str, content: &str) {
+    xml.leaf(
+        "text",
+        &[
+            ("x", &num(x)),
+            ("y", &num(y)),
+            ("text-anchor", anchor),
+            ("font-size", &num(size)),
+            ("fill", color),
+        ],
+        content,
+    );
+}
+
+/// A secondary value axis on the right of the chart: scale function from
+/// value to y and the tick labels.
+fn side_axis(
+    xml: &mut XmlWriter,
+    x: f64,
+    top: f64,
+    bottom: f64,
+    (min, max): (f64, f64),
+    unit: &str,
+    color: &str,
+) -> impl Fn(f64) -> f64 {
+    // A flat series still gets an axis one unit tall around its value
+    let (min, max) = if max - min < 1.0 {
+        let mid = (min + max) / 2.0;
+        (mid - 0.5, mid + 0.5)
+    } else {
+        (min, max)
+    };
+    let step = nice_step(max - min, 6.0);
+    let lo = (min / step).floor() * step;
+    let hi = ((max / step).ceil() * step).max(lo + step);
+    let scale = move |v: f64| bottom - (v - lo) / (hi - lo) * (bottom - top);
+
+    xml.empty(
+        "line",
+        &[
+            ("x1", &num(x)),
+            ("y1", &num(top)),
+            ("x2", &num(x)),
+            ("y2", &num(bottom)),
+            ("stroke", color),
+        ],
+    );
+    for v in ticks(lo, hi, step) {
+        text(xml, x + 6.0, scale(v) + 4.0, "start", 12.0, color, &tick_label(v, step));
+    }
+    text(xml, x + 6.0, top - 10.0, "start", 12.0, color, unit);
+    scale
+}
+
+/// Render the depth profile of `dive` as an SVG document.
+pub fn dive_to_svg(dive: &DiveLog, options: &PlotOptions) -> String {
+    let (w, h) = (options.width as f64, options.height as f64);
+
+    let temps: Vec<(u32, f64)> = dive
+        .samples
+        .iter()
+        .filter_map(|s| s.temp_c.map(|t| (s.time_s, t)))
+        .collect();
+    let show_temp = options.temp && !temps.is_empty();
+    // Per tank: the interpolated line and the actual readings
+    let series: Vec<(u8, Points, Points)> = if options.pressure {
+        dive.pressure_series()
+            .into_iter()
+            .map(|(tank, readings)| {
+                let line = dive.pressure_points(tank, &readings);
+                let dots = readings.iter().map(|r| (r.time_s, r.pressure_bar)).collect();
+                (tank, line, dots)
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let show_pressure = !series.is_empty();
+
+    let left = 70.0;
+    let right = w - 30.0 - 60.0 * (show_temp as u8 + show_pressure as u8) as f64;
+    let top = 70.0;
+    let bottom = h - 60.0;
+
+    let end_s = dive
+        .samples
+        .last()
+        .map_or(dive.duration_seconds, |s| s.time_s)
+        .max(1) as f64;
+    let depth_step = nice_step(dive.max_depth_m.max(1.0), 8.0);
+    let max_depth = (dive.max_depth_m / depth_step).ceil().max(1.0) * depth_step;
+    let px = |t: f64| left + t / end_s * (right - left);
+    let py = |d: f64| top + d / max_depth * (bottom - top);
+
+    let mut xml = XmlWriter::new();
+    let (width, height) = (options.width.to_string(), options.height.to_string());
+    xml.open(
+        "svg",
+        &[
+            ("xmlns", "http://www.w3.org/2000/svg"),
+            ("width", &width),
+            ("height", &height),
+            ("viewBox", &format!("0 0 {} {}", width, height)),
+            ("font-family", FONT),
+        ],
+    );
+    xml.empty("rect", &[("width", "100%"), ("height", "100%"), ("fill", "white")]);
+
+    // Title
+    let (start, _) = dive.start();
+    let mut title = format!("Dive #{} · {}", dive.number, start.format("%Y-%m-%d %H:%M"));
+    if let Some(ref site) = dive.site {
+        title.push_str(&format!(" · {}", site));
+    }
+    text(&mut xml, left, 32.0, "start", 20.0, "#222", &title);
+    text(
+        &mut xml,
+        right,
+        32.0,
+        "end",
+        14.0,
+        "#555",
+        &format!(
+            "max {:.1} m · {}:{:02}",
+            dive.max_depth_m,
+            dive.duration_seconds / 60,
+            dive.duration_seconds % 60
+        ),
+    );
+
+    // Grid and axes
+    xml.open("g", &[("stroke", "#dddddd"), ("stroke-width", "1")]);
+    for depth in ticks(0.0, max_depth, depth_step) {
+        let y = num(py(depth));
+        xml.empty("line", &[("x1", &num(left)), ("y1", &y), ("x2", &num(right)), ("y2", &y)]);
+    }
+    let step = time_step(end_s);
+    for t in ticks(0.0, end_s, step) {
+        let x = num(px(t));
+        xml.empty("line", &[("x1", &x), ("y1", &num(top)), ("x2", &x), ("y2", &num(bottom))]);
+    }
+    xml.close();
+
+    for depth in ticks(0.0, max_depth, depth_step) {
+        let label = tick_label(depth, depth_step);
+        text(&mut xml, left - 8.0, py(depth) + 4.0, "end", 12.0, "#333", &label);
+    }
+    for t in ticks(0.0, end_s, step) {
+        let label = if step >= 60.0 {
+            format!("{}", (t / 60.0) as u32)
+        } else {
+            format!("{}:{:02}", t as u32 / 60, t as u32 % 60)
+        };
+        text(&mut xml, px(t), bottom + 18.0, "middle", 12.0, "#333", &label);
+    }
+    text(&mut xml, left - 8.0, top - 10.0, "end", 12.0, "#333", "m");
+    let time_unit = if step >= 60.0 { "Time (min)" } else { "Time (min:s)" };
+    text(&mut xml, (left + right) / 2.0, bottom + 40.0, "middle", 13.0, "#333", time_unit);
+
+    // Depth area, then the outline coloured by ascent rate
+    let profile: Vec<(f64, f64)> = dive
+        .samples
+        .iter()
+        .map(|s| (px(s.time_s as f64), py(s.depth_m)))
+        .collect();
+    if profile.len() > 1 {
+        let mut area = profile.clone();
+        area.push((profile[profile.len() - 1].0, top));
+        area.push((profile[0].0, top));
+        let points = area
+            .iter()
+            .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+            .collect::<Vec<_>>()
+            .join(" ");
+        xml.empty("polygon", &[("points", &points), ("fill", DEPTH_FILL)]);
+    }
+
+    // Ascent rates only mean something on scuba dives
+    let colour_ascents = dive.dive_mode != DiveMode::Freedive;
+    let mut run: Vec<(f64, f64)> = Vec::new();
+    let mut run_color = DEPTH_COLOR;
+    for (i, pair) in dive.samples.windows(2).enumerate() {
+        let minutes = pair[1].time_s.saturating_sub(pair[0].time_s).max(1) as f64 / 60.0;
+        let rate = (pair[0].depth_m - pair[1].depth_m) / minutes;
+        let color = match rate {
+            r if colour_ascents && r > ASCENT_FAST => ASCENT_FAST_COLOR,
+            r if colour_ascents && r > ASCENT_WARN => ASCENT_WARN_COLOR,
+            _ => DEPTH_COLOR,
+        };
+        if color != run_color && !run.is_empty() {
+            polyline(&mut xml, &run, run_color, 2.5, None);
+            run = vec![*run.last().unwrap()];
+        }
+        if run.is_empty() {
+            run.push(profile[i]);
+        }
+        run.push(profile[i + 1]);
+        run_color = color;
+    }
+    if run.len() > 1 {
+        polyline(&mut xml, &run, run_color, 2.5, None);
+    }
+
+    // Temperature and pressure overlays on their own axes
+    let mut axis_x = right;
+    let mut legend: Vec<(String, &str, Option<&str>)> =
+        vec![("Depth".to_string(), DEPTH_COLOR, None)];
+    if show_temp {
+        let range = temps.iter().fold((f64::MAX, f64::MIN), |(lo, hi), (_, t)| {
+            (lo.min(*t), hi.max(*t))
+        });
+        let scale = side_axis(&mut xml, axis_x, top, bottom, range, "°C", TEMP_COLOR);
+        let points: Vec<(f64, f64)> =
+            temps.iter().map(|&(t, c)| (px(t as f64), scale(c))).collect();
+        polyline(&mut xml, &points, TEMP_COLOR, 1.5, Some(TEMP_DASH));
+        legend.push(("Temperature".to_string(), TEMP_COLOR, Some(TEMP_DASH)));
+        axis_x += 60.0;
+    }
+    if show_pressure {
+        let range = series
+            .iter()
+            .flat_map(|(_, line, _)| line.iter().map(|(_, p)| *p))
+            .fold((f64::MAX, f64::MIN), |(lo, hi), p| (lo.min(p), hi.max(p)));
+        let scale = side_axis(&mut xml, axis_x, top, bottom, range, "bar", "#555");
+        for (i, (tank, line, dots)) in series.iter().enumerate() {
+            let color = TANK_COLORS[i % TANK_COLORS.len()];
+            let points: Vec<(f64, f64)> =
+                line.iter().map(|&(t, p)| (px(t as f64), scale(p))).collect();
+            polyline(&mut xml, &points, color, 1.5, None);
+            for &(t, p) in dots {
+                xml.empty(
+                    "circle",
+                    &[
+                        ("cx", &num(px(t as f64))),
+                        ("cy", &num(scale(p))),
+                        ("r", "2.5"),
+                        ("fill", color),
+                    ],
+                );
+            }
+            legend.push((dive.tank_label(*tank), color, None));
+        }
+    }
+
+    // Markers: deepest point and, on freedive sessions, each apnea start.
+    // Device events (alarms, gas switches) are not decoded.
+    if let Some(deepest) = dive
+        .samples
+        .iter()
+        .max_by(|a, b| a.depth_m.total_cmp(&b.depth_m))
+    {
+        let (x, y) = (px(deepest.time_s as f64), py(deepest.depth_m));
+        xml.empty(
+            "path",
+            &[
+                ("d", &format!("M{:.1},{:.1} l-6,10 h12 z", x, y + 4.0)),
+                ("fill", "#222"),
+            ],
+        );
+        text(&mut xml, x, y + 28.0, "middle", 12.0, "#222", &format!("{:.1} m", deepest.depth_m));
+    }
+    let apneas = if dive.apneas.is_empty() && dive.dive_mode == DiveMode::Freedive {
+        freedive::split_apneas(&dive.samples)
+    } else {
+        dive.apneas.clone()
+    };
+    for apnea in &apneas {
+        let x = px(apnea.start_s as f64);
+        xml.empty(
+            "line",
+            &[
+                ("x1", &num(x)),
+                ("y1", &num(top)),
+                ("x2", &num(x)),
+                ("y2", &num(bottom)),
+                ("stroke", "#888"),
+                ("stroke-dasharray", "4 3"),
+            ],
+        );
+        text(&mut xml, x + 3.0, top + 14.0, "start", 11.0, "#555", &format!("A{}", apnea.number));
+    }
+
+    // Legend, with the ascent rate bands on scuba dives
+    if colour_ascents {
+        legend.push((format!("Ascent > {} m/min", ASCENT_WARN), ASCENT_WARN_COLOR, None));
+        legend.push((format!("Ascent > {} m/min", ASCENT_FAST), ASCENT_FAST_COLOR, None));
+    }
+    let legend_x = right - 170.0;
+    let legend_y = bottom - 12.0 - 18.0 * legend.len() as f64;
+    xml.empty(
+        "rect",
+        &[
+            ("x", &num(legend_x - 8.0)),
+            ("y", &num(legend_y - 6.0)),
+            ("width", "170"),
+            ("height", &num(18.0 * legend.len() as f64 + 6.0)),
+            ("fill", "white"),
+            ("fill-opacity", "0.85"),
+            ("stroke", "#cccccc"),
+        ],
+    );
+    for (i, (label, color, dash)) in legend.iter().enumerate() {
+        let y = legend_y + 18.0 * i as f64 + 6.0;
+        polyline(&mut xml, &[(legend_x, y), (legend_x + 20.0, y)], color, 3.0, *dash);
+        text(&mut xml, legend_x + 28.0, y + 4.0, "start", 12.0, "#333", label);
+    }
+
+    xml.empty(
+        "rect",
+        &[
+            ("x", &num(left)),
+            ("y", &num(top)),
+            ("width", &num(right - left)),
+            ("height", &num(bottom - top)),
+            ("fill", "none"),
+            ("stroke", "#333"),
+        ],
+    );
+    xml.close();
+    xml.finish()
+}
+
+/// Rasterize an SVG document to PNG with the system fonts, falling back to
+/// the bundled DejaVu Sans.
+pub fn svg_to_png(svg: &str, scale: f32) -> Result<Vec<u8>> {
+    let mut options = resvg::usvg::Options::default();
+    let fontdb = options.fontdb_mut();
+    fontdb.load_system_fonts();
+    fontdb.load_font_data(FALLBACK_FONT.to_vec());
+    let tree = resvg::usvg::Tree::from_str(svg, &options).context("Invalid SVG")?;
+    let size = tree.size().to_int_size().scale_by(scale).context("Invalid image size")?;
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(size.width(), size.height())
+        .context("Invalid image size")?;
+    resvg::render(
+        &tree,
+        resvg::tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    pixmap.encode_png().context("Failed to encode PNG")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tick_labels_follow_the_step() {
+        let labels: Vec<String> = ticks(0.0, 0.6, 0.1).map(|v| tick_label(v, 0.1)).collect();
+        assert_eq!(labels, ["0.0", "0.1", "0.2", "0.3", "0.4", "0.5", "0.6"]);
+        let labels: Vec<String> = ticks(150.0, 200.0, 10.0).map(|v| tick_label(v, 10.0)).collect();
+        assert_eq!(labels, ["150", "160", "170", "180", "190", "200"]);
+    }
+
+    #[test]
+    fn flat_series_gets_a_usable_axis() {
+        let mut xml = XmlWriter::new();
+        xml.open("g", &[]);
+        let scale = side_axis(&mut xml, 0.0, 0.0, 100.0, (27.0, 27.0), "°C", TEMP_COLOR);
+        xml.close();
+        let svg = xml.finish();
+        assert!((scale(27.0) - 50.0).abs() < 10.0);
+        assert!(svg.contains(">27.0<"));
+        assert!(svg.matches("<text").count() > 3);
+    }
+}
@@ -0,0 +1,687 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::types::{DiveDetails, DiveLog};
+
+/// A dive from the SSI logbook CSV export.
+pub struct SsiRecord {
+    /// Line of the record in the CSV file (header is line 1).
+    pub row: usize,
+    pub datetime: NaiveDateTime,
+    pub site: String,
+    pub country: String,
+    pub buddy: String,
+    pub max_depth_m: Option<f64>,
+    pub duration_min: Option<f64>,
+    pub notes: String,
+    pub details: DiveDetails,
+}
+
+/// Languages of the SSI logbook CSV export with built-in column headings.
+/// Only headings seen in a real export are built in; other languages and the
+/// depth, duration and detail columns need a `--mapping` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SsiLanguage {
+    Fr,
+}
+
+impl SsiLanguage {
+    const ALL: [SsiLanguage; 1] = [SsiLanguage::Fr];
+
+    fn name(self) -> &'static str {
+        match self {
+            SsiLanguage::Fr => "French",
+        }
+    }
+
+    /// Column headings of the export in this language.
+    pub fn mapping(self) -> SsiMapping {
+        let (date, site, country, buddy, date_format) = match self {
+            SsiLanguage::Fr => (
+                "Date / Temps",
+                "Site de plongée",
+                "Pays",
+                "Equipier / Instructor / Center",
+                "%d. %b %Y %H:%M",
+            ),
+        };
+        SsiMapping {
+            date: date.to_string(),
+            site: Some(site.to_string()),
+            country: Some(country.to_string()),
+            buddy: Some(buddy.to_string()),
+            max_depth: None,
+            duration: None,
+            dive_type: None,
+            water_body: None,
+            water_type: None,
+            visibility: None,
+            weather: None,
+            air_temp: None,
+            entry: None,
+            weight: None,
+            notes: None,
+            rating: None,
+            date_formats: vec![date_format.to_string()],
+            delimiter: None,
+        }
+    }
+}
+
+/// Which CSV column holds which field, for exports in other languages or
+/// layouts. Loaded from a TOML `--mapping` file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SsiMapping {
+    /// Heading of the dive date / time column.
+    pub date: String,
+    #[serde(default)]
+    pub site: Option<String>,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub buddy: Option<String>,
+    /// Maximum depth in metres, used to tell apart dives close in time.
+    /// Ignored when the export has no such column.
+    #[serde(default)]
+    pub max_depth: Option<String>,
+    /// Dive time in minutes ("45") or hours and minutes ("0:45").
+    /// Ignored when the export has no such column.
+    #[serde(default)]
+    pub duration: Option<String>,
+    // Dive details, all ignored when the export has no such column
+    #[serde(default)]
+    pub dive_type: Option<String>,
+    #[serde(default)]
+    pub water_body: Option<String>,
+    #[serde(default)]
+    pub water_type: Option<String>,
+    /// Visibility in metres.
+    #[serde(default)]
+    pub visibility: Option<String>,
+    #[serde(default)]
+    pub weather: Option<String>,
+    /// Air temperature in °C.
+    #[serde(default)]
+    pub air_temp: Option<String>,
+    #[serde(default)]
+    pub entry: Option<String>,
+    /// Weight (lead) in kg.
+    #[serde(default)]
+    pub weight: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Rating from 1 to 5, as a number or stars.
+    #[serde(default)]
+    pub rating: Option<String>,
+    /// chrono formats tried before the common ones. Month names may be in any
+    /// supported language.
+    #[serde(default)]
+    pub date_formats: Vec<String>,
+    /// Field separator (default: detected from the header row).
+    #[serde(default)]
+    pub delimiter: Option<char>,
+}
+
+impl SsiMapping {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+}
+
+/// Date formats tried after the mapping's own, once month names have been
+/// translated to English.
+const DATE_FORMATS: &[&str] = &[
+    "%d. %b %Y %H:%M",
+    "%d %b %Y %H:%M",
+    "%b %d, %Y %H:%M",
+    "%b %d %Y %H:%M",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+];
+
+/// Month names and abbreviations in the supported languages.
+const MONTHS: [&[&str]; 12] = [
+    &["jan", "january", "janvier", "janv", "januar", "jän", "enero", "ene", "gennaio", "gen", "januari"],
+    &["feb", "february", "février", "fevrier", "févr", "fevr", "februar", "febrero", "febbraio", "februari"],
+    &["mar", "march", "mars", "märz", "maerz", "mär", "marzo", "maart", "mrt"],
+    &["apr", "april", "avril", "avr", "abril", "abr", "aprile"],
+    &["may", "mai", "mayo", "maggio", "mag", "mei"],
+    &["jun", "june", "juin", "juni", "junio", "giugno", "giu"],
+    &["jul", "july", "juillet", "juil", "juli", "julio", "luglio", "lug"],
+    &["aug", "august", "août", "aout", "agosto", "ago", "augustus"],
+    &["sep", "sept", "september", "septembre", "septiembre", "set", "settembre"],
+    &["oct", "october", "octobre", "okt", "oktober", "octubre", "ottobre", "ott"],
+    &["nov", "november", "novembre", "noviembre"],
+    &["dec", "december", "décembre", "decembre", "déc", "dez", "dezember", "diciembre", "dic", "dicembre"],
+];
+
+const ENGLISH_MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Replace localized month names with English abbreviations for `%b`.
+fn english_months(date: &str) -> String {
+    let mut out = String::new();
+    let mut word = String::new();
+    let flush = |word: &mut String, out: &mut String| {
+        let lower = word.to_lowercase();
+        match MONTHS.iter().position(|names| names.contains(&lower.as_str())) {
+            Some(month) => out.push_str(ENGLISH_MONTHS[month]),
+            None => out.push_str(word),
+        }
+        word.clear();
+    };
+    for c in date.chars() {
+        if c.is_alphabetic() {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut out);
+            out.push(c);
+        }
+    }
+    flush(&mut word, &mut out);
+    out
+}
+
+fn parse_date(text: &str, mapping: &SsiMapping) -> Option<NaiveDateTime> {
+    let text = english_months(&text.split_whitespace().collect::<Vec<_>>().join(" "));
+    // Abbreviations may carry a dot ("4. mars 2025", "4 Mar. 2025")
+    let candidates = [text.clone(), text.replace(". ", " ")];
+    mapping
+        .date_formats
+        .iter()
+        .map(String::as_str)
+        .chain(DATE_FORMATS.iter().copied())
+        .find_map(|format| {
+            candidates
+                .iter()
+                .find_map(|t| NaiveDateTime::parse_from_str(t, format).ok())
+        })
+}
+
+/// Split CSV text into records, handling quoted fields with escaped quotes
+/// and line breaks.
+pub fn parse_csv(contents: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = contents.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    current.push('"');
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                current.push(c);
+            }
+        } else if c == '"' {
+            in_quotes = true;
+        } else if c == delimiter {
+            fields.push(std::mem::take(&mut current));
+        } else if c == '\n' || c == '\r' {
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            fields.push(std::mem::take(&mut current));
+            records.push(std::mem::take(&mut fields));
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() || !fields.is_empty() {
+        fields.push(current);
+        records.push(fields);
+    }
+    records
+}
+
+/// Field separator of a CSV file, guessed from its first line.
+fn detect_delimiter(contents: &str) -> char {
+    let header = contents.lines().next().unwrap_or_default();
+    [',', ';', '\t']
+        .into_iter()
+        .max_by_key(|d| header.matches(*d).count())
+        .unwrap()
+}
+
+/// Clean buddy field: normalize whitespace, strip trailing "Sirius"/"Mares", trim.
+fn clean_buddy(raw: &str) -> String {
+    let normalized: String = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+    let trimmed = normalized
+        .trim_end_matches("Sirius")
+        .trim_end_matches("Mares")
+        .trim();
+    trimmed.to_string()
+}
+
+/// A number such as "18,5 m", "18.5" or "-2 °C".
+fn parse_number(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(rest) => (-1.0, rest.trim_start()),
+        None => (1.0, text),
+    };
+    let number: String = digits
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.' || *c == ',')
+        .map(|c| if c == ',' { '.' } else { c })
+        .collect();
+    number.parse::<f64>().ok().map(|n| sign * n)
+}
+
+/// Rating from 1 to 5, written as "4", "4/5" or "★★★★".
+fn parse_rating(text: &str) -> Option<u8> {
+    let stars = text.chars().filter(|c| matches!(c, '★' | '*')).count();
+    let rating = if stars > 0 {
+        stars as f64
+    } else {
+        parse_number(text)?
+    };
+    (1.0..=5.0)
+        .contains(&rating.round())
+        .then_some(rating.round() as u8)
+}
+
+/// A text field, `None` when empty.
+fn text(field: &str) -> Option<String> {
+    (!field.is_empty()).then(|| field.to_string())
+}
+
+/// Minutes from "45", "45 min", "0:45" or "0:45:00".
+fn parse_minutes(text: &str) -> Option<f64> {
+    let parts: Vec<&str> = text.trim().split(':').collect();
+    match parts.as_slice() {
+        [minutes] => parse_number(minutes),
+        [hours, minutes] => Some(hours.trim().parse::<f64>().ok()? * 60.0 + parse_number(minutes)?),
+        [hours, minutes, seconds] => Some(
+            hours.trim().parse::<f64>().ok()? * 60.0
+                + minutes.trim().parse::<f64>().ok()?
+                + parse_number(seconds)? / 60.0,
+        ),
+        _ => None,
+    }
+}
+
+fn normalize_heading(h: &str) -> String {
+    h.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+fn column(headers: &[String], heading: &str) -> Option<usize> {
+    let heading = normalize_heading(heading);
+    headers.iter().position(|h| *h == heading)
+}
+
+/// The built-in language whose headings match the header row best.
+fn detect_language(headers: &[String]) -> Option<SsiLanguage> {
+    SsiLanguage::ALL
+        .into_iter()
+        .map(|lang| {
+            let mapping = lang.mapping();
+            let headings = [Some(&mapping.date), mapping.site.as_ref(), mapping.country.as_ref(), mapping.buddy.as_ref()];
+            let score = headings
+                .into_iter()
+                .flatten()
+                .filter(|h| column(headers, h).is_some())
+                .count();
+            (lang, score, column(headers, &mapping.date).is_some())
+        })
+        .filter(|(_, _, has_date)| *has_date)
+        .max_by_key(|(_, score, _)| *score)
+        .map(|(lang, _, _)| lang)
+}
+
+/// Parse an SSI CSV export into records. Without a `mapping` the export
+/// language is detected from the header row.
+pub fn parse_ssi_csv(contents: &str, mapping: Option<SsiMapping>) -> Result<Vec<SsiRecord>> {
+    let delimiter = mapping
+        .as_ref()
+        .and_then(|m| m.delimiter)
+        .unwrap_or_else(|| detect_delimiter(contents));
+    let mut rows = parse_csv(contents, delimiter).into_iter();
+
+    let Some(header) = rows.next() else {
+        return Ok(Vec::new());
+    };
+    let headers: Vec<String> = header.iter().map(|h| normalize_heading(h)).collect();
+
+    let mapping = match mapping {
+        Some(mapping) => mapping,
+        None => {
+            let lang = detect_language(&headers).with_context(|| {
+                format!(
+                    "Unrecognised SSI CSV header {:?}; pass --lang or a --mapping file",
+                    header
+                )
+            })?;
+            eprintln!("Detected {} SSI export", lang.name());
+            lang.mapping()
+        }
+    };
+
+    let date_col = column(&headers, &mapping.date)
+        .with_context(|| format!("SSI CSV has no {:?} column", mapping.date))?;
+    let optional = |heading: &Option<String>| -> Result<Option<usize>> {
+        match heading {
+            Some(h) => column(&headers, h)
+                .map(Some)
+                .with_context(|| format!("SSI CSV has no {:?} column", h)),
+            None => Ok(None),
+        }
+    };
+    let site_col = optional(&mapping.site)?;
+    let country_col = optional(&mapping.country)?;
+    let buddy_col = optional(&mapping.buddy)?;
+    let lenient = |heading: &Option<String>| heading.as_ref().and_then(|h| column(&headers, h));
+    let depth_col = lenient(&mapping.max_depth);
+    let duration_col = lenient(&mapping.duration);
+    let dive_type_col = lenient(&mapping.dive_type);
+    let water_body_col = lenient(&mapping.water_body);
+    let water_type_col = lenient(&mapping.water_type);
+    let visibility_col = lenient(&mapping.visibility);
+    let weather_col = lenient(&mapping.weather);
+    let air_temp_col = lenient(&mapping.air_temp);
+    let entry_col = lenient(&mapping.entry);
+    let weight_col = lenient(&mapping.weight);
+    let notes_col = lenient(&mapping.notes);
+    let rating_col = lenient(&mapping.rating);
+
+    let mut records = Vec::new();
+    for (row_num, fields) in rows.enumerate() {
+        if fields.iter().all(|f| f.trim().is_empty()) {
+            continue;
+        }
+        let field = |col: Option<usize>| {
+            col.and_then(|c| fields.get(c))
+                .map(|f| f.trim())
+                .unwrap_or_default()
+        };
+        let Some(date) = fields.get(date_col) else {
+            eprintln!("Warning: skipping CSV row {} (not enough fields)", row_num + 2);
+            continue;
+        };
+
+        let Some(datetime) = parse_date(date, &mapping) else {
+            eprintln!("Warning: skipping CSV row {} (bad date {:?})", row_num + 2, date);
+            continue;
+        };
+
+        records.push(SsiRecord {
+            row: row_num + 2,
+            datetime,
+            site: field(site_col).to_string(),
+            country: field(country_col).to_string(),
+            buddy: clean_buddy(field(buddy_col)),
+            max_depth_m: parse_number(field(depth_col)),
+            duration_min: parse_minutes(field(duration_col)),
+            notes: field(notes_col).to_string(),
+            details: DiveDetails {
+                dive_type: text(field(dive_type_col)),
+                water_body: text(field(water_body_col)),
+                water_type: text(field(water_type_col)),
+                visibility_m: parse_number(field(visibility_col)),
+                weather: text(field(weather_col)),
+                air_temp_c: parse_number(field(air_temp_col)),
+                entry: text(field(entry_col)),
+                weight_kg: parse_number(field(weight_col)),
+                rating: parse_rating(field(rating_col)),
+            },
+        });
+    }
+
+    Ok(records)
+}
+
+/// Result of pairing dives with SSI records, as indices into both lists.
+pub struct Matching {
+    /// Dive, record and the minutes between their start times.
+    pub matched: Vec<(usize, usize, i64)>,
+    /// Dives and records that are equally good candidates for each other.
+    pub ambiguous: Vec<(Vec<usize>, Vec<usize>)>,
+    pub unmatched_dives: Vec<usize>,
+    pub unmatched_records: Vec<usize>,
+}
+
+/// How far the SSI record's depth and duration are from the dive's, when the
+/// export has them. Lower is better.
+fn mismatch(dive: &DiveLog, record: &SsiRecord) -> u64 {
+    let depth = record
+        .max_depth_m
+        .map_or(0.0, |d| (d - dive.max_depth_m).abs() * 10.0);
+    let duration = record
+        .duration_min
+        .map_or(0.0, |m| (m - dive.duration_seconds as f64 / 60.0).abs());
+    (depth + duration).round() as u64
+}
+
+/// Pair each dive with the SSI record closest in time, within `tolerance_min`
+/// minutes after shifting SSI times by `shift_min`. Equally close candidates
+/// are told apart by depth and duration; if that is not enough they are
+/// reported as ambiguous and left unmatched.
+pub fn match_dives(
+    dives: &[DiveLog],
+    records: &[SsiRecord],
+    tolerance_min: i64,
+    shift_min: i64,
+) -> Matching {
+    use chrono::Timelike;
+
+    // SSI times have minute precision
+    let mut pairs: Vec<((i64, u64), usize, usize)> = Vec::new();
+    for (d, dive) in dives.iter().enumerate() {
+        let (start, _) = dive.start();
+        let start = start.with_second(0).unwrap_or(start);
+        for (r, record) in records.iter().enumerate() {
+            let ssi_start = record.datetime + chrono::Duration::minutes(shift_min);
+            let minutes = (start - ssi_start).num_minutes();
+            if minutes.abs() <= tolerance_min {
+                pairs.push(((minutes.abs(), mismatch(dive, record)), d, r));
+            }
+        }
+    }
+    pairs.sort();
+
+    let mut dive_done = vec![false; dives.len()];
+    let mut record_done = vec![false; records.len()];
+    let mut matching = Matching {
+        matched: Vec::new(),
+        ambiguous: Vec::new(),
+        unmatched_dives: Vec::new(),
+        unmatched_records: Vec::new(),
+    };
+    for &(score, d, r) in &pairs {
+        if dive_done[d] || record_done[r] {
+            continue;
+        }
+        let rivals: Vec<(usize, usize)> = pairs
+            .iter()
+            .filter(|&&(s, d2, r2)| s == score && (d2 == d) != (r2 == r))
+            .filter(|&&(_, d2, r2)| !dive_done[d2] && !record_done[r2])
+            .map(|&(_, d2, r2)| (d2, r2))
+            .collect();
+        if rivals.is_empty() {
+            let (start, _) = dives[d].start();
+            let minutes = (start.with_second(0).unwrap_or(start)
+                - records[r].datetime
+                - chrono::Duration::minutes(shift_min))
+            .num_minutes();
+            matching.matched.push((d, r, minutes));
+            dive_done[d] = true;
+            record_done[r] = true;
+        } else {
+            let mut group_dives = vec![d];
+            let mut group_records = vec![r];
+            for (d2, r2) in rivals {
+                if !group_dives.contains(&d2) {
+                    group_dives.push(d2);
+                }
+                if !group_records.contains(&r2) {
+                    group_records.push(r2);
+                }
+            }
+            for &d in &group_dives {
+                dive_done[d] = true;
+            }
+            for &r in &group_records {
+                record_done[r] = true;
+            }
+            matching.ambiguous.push((group_dives, group_records));
+        }
+    }
+
+    let in_ambiguous_dive = |d: usize| matching.ambiguous.iter().any(|(ds, _)| ds.contains(&d));
+    let in_ambiguous_record = |r: usize| matching.ambiguous.iter().any(|(_, rs)| rs.contains(&r));
+    let unmatched_dives = (0..dives.len())
+        .filter(|&d| !matching.matched.iter().any(|m| m.0 == d) && !in_ambiguous_dive(d))
+        .collect();
+    let unmatched_records = (0..records.len())
+        .filter(|&r| !matching.matched.iter().any(|m| m.1 == r) && !in_ambiguous_record(r))
+        .collect();
+    matching.unmatched_dives = unmatched_dives;
+    matching.unmatched_records = unmatched_records;
+    matching
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Header row of the French export the original correlate command was
+    /// written against (site, country and date in columns 1-3, buddy in 9).
+    const FRENCH_HEADER: &str = "N°,Site de plongée,Pays,Date / Temps,Type,Profondeur,Durée,\
+                                 Température,Visibilité,Equipier / Instructor / Center";
+
+    #[test]
+    fn french_export_is_detected() {
+        let csv = format!(
+            "{}\n12,Blue Corner,Palau,4. Mar 2025 10:15,,,,,,\"Jane  Doe Mares\"\n",
+            FRENCH_HEADER
+        );
+        let records = parse_ssi_csv(&csv, None).unwrap();
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.datetime.to_string(), "2025-03-04 10:15:00");
+        assert_eq!(record.site, "Blue Corner");
+        assert_eq!(record.country, "Palau");
+        assert_eq!(record.buddy, "Jane Doe");
+        // Not built in: only read through a mapping file
+        assert_eq!(record.max_depth_m, None);
+    }
+
+    #[test]
+    fn other_languages_need_a_mapping() {
+        let csv = "Date / Time,Dive Site,Country\n\"Mar 4, 2025 10:15\",Blue Corner,Palau\n";
+        let err = parse_ssi_csv(csv, None).err().unwrap();
+        assert!(err.to_string().contains("--mapping"));
+    }
+
+    fn at(datetime: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn record(datetime: &str, max_depth_m: Option<f64>) -> SsiRecord {
+        SsiRecord {
+            row: 2,
+            datetime: at(datetime),
+            site: String::new(),
+            country: String::new(),
+            buddy: String::new(),
+            max_depth_m,
+            duration_min: None,
+            notes: String::new(),
+            details: DiveDetails::default(),
+        }
+    }
+
+    /// The sample dive starts at 10:15 and goes to 12.3 m.
+    fn match_one(records: &[SsiRecord], tolerance_min: i64, shift_min: i64) -> Matching {
+        match_dives(&[DiveLog::sample_at(12, "2025-03-04 10:15")], records, tolerance_min, shift_min)
+    }
+
+    #[test]
+    fn exact_match() {
+        let matching = match_one(&[record("2025-03-04 10:15", None)], 5, 0);
+        assert_eq!(matching.matched, [(0, 0, 0)]);
+        assert!(matching.unmatched_dives.is_empty() && matching.unmatched_records.is_empty());
+    }
+
+    #[test]
+    fn matches_within_tolerance() {
+        let matching = match_one(&[record("2025-03-04 10:18", None)], 5, 0);
+        assert_eq!(matching.matched, [(0, 0, -3)]);
+        let matching = match_one(&[record("2025-03-04 10:10", None)], 5, 0);
+        assert_eq!(matching.matched, [(0, 0, 5)]);
+    }
+
+    #[test]
+    fn no_match_just_outside_tolerance() {
+        let matching = match_one(&[record("2025-03-04 10:21", None)], 5, 0);
+        assert!(matching.matched.is_empty());
+        assert_eq!(matching.unmatched_dives, [0]);
+        assert_eq!(matching.unmatched_records, [0]);
+    }
+
+    #[test]
+    fn equal_times_are_told_apart_by_depth() {
+        let records = [
+            record("2025-03-04 10:13", Some(30.0)),
+            record("2025-03-04 10:17", Some(12.0)),
+        ];
+        let matching = match_one(&records, 5, 0);
+        assert_eq!(matching.matched, [(0, 1, -2)]);
+        assert!(matching.ambiguous.is_empty());
+        assert_eq!(matching.unmatched_records, [0]);
+    }
+
+    #[test]
+    fn real_ties_are_ambiguous() {
+        let records = [record("2025-03-04 10:13", None), record("2025-03-04 10:17", None)];
+        let matching = match_one(&records, 5, 0);
+        assert!(matching.matched.is_empty());
+        assert_eq!(matching.ambiguous, [(vec![0], vec![0, 1])]);
+        assert!(matching.unmatched_dives.is_empty() && matching.unmatched_records.is_empty());
+    }
+
+    #[test]
+    fn negative_shift_moves_ssi_times_back() {
+        // SSI log kept one hour ahead of the dive computer
+        let records = [record("2025-03-04 11:16", None)];
+        assert!(match_one(&records, 5, 0).matched.is_empty());
+        assert_eq!(match_one(&records, 5, -60).matched, [(0, 0, -1)]);
+    }
+
+    #[test]
+    fn parses_localized_month_names() {
+        let mapping = SsiLanguage::Fr.mapping();
+        assert_eq!(parse_date("4. mars 2025 10:12", &mapping), Some(at("2025-03-04 10:12")));
+        assert_eq!(parse_date("4. Mar. 2025 10:12", &mapping), Some(at("2025-03-04 10:12")));
+    }
+
+    #[test]
+    fn mapping_formats_decide_day_and_month_order() {
+        let with_format = |format: &str| SsiMapping {
+            date_formats: vec![format.to_string()],
+            ..SsiLanguage::Fr.mapping()
+        };
+        let us = with_format("%m/%d/%Y %H:%M");
+        let eu = with_format("%d/%m/%Y %H:%M");
+        assert_eq!(parse_date("03/04/2025 10:12", &us), Some(at("2025-03-04 10:12")));
+        assert_eq!(parse_date("03/04/2025 10:12", &eu), Some(at("2025-04-03 10:12")));
+        // Only one reading is a valid date
+        assert_eq!(parse_date("13/04/2025 10:12", &us), None);
+        assert_eq!(parse_date("13/04/2025 10:12", &eu), Some(at("2025-04-13 10:12")));
+    }
+}
@@ -0,0 +1,501 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use rusqlite::{params, Connection, Transaction};
+
+use crate::types::*;
+
+const DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS devices (
+    id INTEGER PRIMARY KEY,
+    model TEXT NOT NULL,
+    serial TEXT NOT NULL DEFAULT '',
+    UNIQUE (model, serial)
+);
+CREATE TABLE IF NOT EXISTS sites (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    country TEXT NOT NULL DEFAULT '',
+    UNIQUE (name, country)
+);
+CREATE TABLE IF NOT EXISTS dives (
+    id INTEGER PRIMARY KEY,
+    number INTEGER NOT NULL,
+    datetime TEXT NOT NULL,
+    start_time TEXT,
+    timezone TEXT,
+    device_id INTEGER REFERENCES devices (id),
+    duration_seconds INTEGER NOT NULL,
+    max_depth_m REAL NOT NULL,
+    min_temp_c REAL,
+    max_temp_c REAL,
+    dive_mode TEXT NOT NULL,
+    site_id INTEGER REFERENCES sites (id),
+    country TEXT,
+    buddy TEXT,
+    notes TEXT,
+    details TEXT,
+    source TEXT NOT NULL DEFAULT '',
+    tanks TEXT,
+    apneas TEXT,
+    UNIQUE (source, number, datetime)
+);
+CREATE TABLE IF NOT EXISTS gas_mixes (
+    dive_id INTEGER NOT NULL REFERENCES dives (id) ON DELETE CASCADE,
+    idx INTEGER NOT NULL,
+    o2 INTEGER NOT NULL,
+    PRIMARY KEY (dive_id, idx)
+);
+CREATE TABLE IF NOT EXISTS samples (
+    dive_id INTEGER NOT NULL REFERENCES dives (id) ON DELETE CASCADE,
+    time_s INTEGER NOT NULL,
+    depth_m REAL NOT NULL,
+    temp_c REAL,
+    pressure_bar REAL
+);
+CREATE INDEX IF NOT EXISTS samples_dive ON samples (dive_id, time_s);
+";
+
+/// Where the logbook is kept: the `dives.json` file, or a SQLite database
+/// selected with `--db`.
+pub enum Store {
+    Json(PathBuf),
+    Sqlite(PathBuf),
+}
+
+impl Store {
+    pub fn new(db: Option<PathBuf>, json: PathBuf) -> Self {
+        match db {
+            Some(db) => Store::Sqlite(db),
+            None => Store::Json(json),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            Store::Json(path) | Store::Sqlite(path) => path,
+        }
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path().exists()
+    }
+
+    /// Read all dives, oldest first by UTC start time (local clock when the
+    /// zone is unknown), whichever the store.
+    pub fn load(&self) -> Result<DiveData> {
+        let mut data = self.load_unsorted()?;
+        data.dives.sort_by_key(|d| d.start_utc());
+        Ok(data)
+    }
+
+    fn load_unsorted(&self) -> Result<DiveData> {
+        let path = self.path();
+        match self {
+            Store::Json(_) => {
+                let contents = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                serde_json::from_str(&contents)
+                    .with_context(|| format!("Failed to parse {}", path.display()))
+            }
+            Store::Sqlite(_) => {
+                if !path.exists() {
+                    anyhow::bail!("Database {} does not exist", path.display());
+                }
+                let conn = open(path)?;
+                let dives = load_dives(&conn)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                Ok(DiveData { dives })
+            }
+        }
+    }
+
+    /// Copy the logbook to `{name}.{timestamp}.bak` next to it.
+    pub fn backup(&self) -> Result<PathBuf> {
+        let path = self.path();
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}.bak", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+        let backup = path.with_file_name(name);
+        let failed = || format!("Failed to back up {} to {}", path.display(), backup.display());
+        match self {
+            Store::Json(_) => {
+                std::fs::copy(path, &backup).with_context(failed)?;
+            }
+            // A copy of the database file would miss commits still in the WAL
+            Store::Sqlite(_) => {
+                let target = backup.to_str().context("Backup path is not valid UTF-8")?;
+                open(path)?
+                    .execute("VACUUM INTO ?1", [target])
+                    .with_context(failed)?;
+            }
+        }
+        Ok(backup)
+    }
+
+    /// Add or replace `dives`, matched by source, number and start time.
+    /// The JSON file is rewritten as a whole; the database only touches the
+    /// given dives, in one transaction.
+    pub fn save_dives(&self, dives: &[DiveLog]) -> Result<()> {
+        let path = self.path();
+        match self {
+            Store::Json(_) => {
+                let mut data = if path.exists() {
+                    self.load()?
+                } else {
+                    DiveData { dives: Vec::new() }
+                };
+                for dive in dives {
+                    match data.dives.iter_mut().find(|d| same_dive(d, dive)) {
+                        Some(existing) => *existing = dive.clone(),
+                        None => data.dives.push(dive.clone()),
+                    }
+                }
+                data.dives.sort_by_key(|d| d.start_utc());
+                let json = serde_json::to_string_pretty(&data)?;
+                std::fs::write(path, &json)?;
+            }
+            Store::Sqlite(_) => {
+                let mut conn = open(path)?;
+                let tx = conn.transaction()?;
+                for dive in dives {
+                    upsert_dive(&tx, dive)
+                        .with_context(|| format!("Failed to store dive #{}", dive.number))?;
+                }
+                tx.commit()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn same_dive(a: &DiveLog, b: &DiveLog) -> bool {
+    a.number == b.number && a.datetime == b.datetime && a.source == b.source
+}
+
+fn open(path: &Path) -> Result<Connection> {
+    let conn = Connection::open(path)
+        .with_context(|| format!("Failed to open database {}", path.display()))?;
+    // WAL lets the viewer read while a download writes
+    conn.busy_timeout(Duration::from_secs(10))?;
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.pragma_update(None, "foreign_keys", "ON")?;
+    conn.execute_batch(SCHEMA)?;
+    add_missing_column(&conn, "dives", "notes", "TEXT")?;
+    add_missing_column(&conn, "dives", "details", "TEXT")?;
+    Ok(conn)
+}
+
+/// Add a column introduced after the database was created.
+fn add_missing_column(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    let exists = conn
+        .prepare(&format!("SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1", table))?
+        .exists([column])?;
+    if !exists {
+        conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl))?;
+    }
+    Ok(())
+}
+
+fn format_datetime(dt: NaiveDateTime) -> String {
+    dt.format(DATETIME_FORMAT).to_string()
+}
+
+fn parse_datetime(text: &str) -> rusqlite::Result<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(text, DATETIME_FORMAT)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+/// Serialize through serde, e.g. "nitrox" for `DiveMode::Nitrox`.
+fn to_text<T: serde::Serialize>(value: &T) -> Result<String> {
+    Ok(match serde_json::to_value(value)? {
+        serde_json::Value::String(s) => s,
+        other => other.to_string(),
+    })
+}
+
+fn from_text<T: serde::de::DeserializeOwned>(text: String) -> Result<T> {
+    Ok(serde_json::from_value(serde_json::Value::String(text))?)
+}
+
+fn upsert_dive(tx: &Transaction, dive: &DiveLog) -> Result<()> {
+    let source = dive.source.as_deref().unwrap_or("");
+    let datetime = format_datetime(dive.datetime);
+    tx.execute(
+        "DELETE FROM dives WHERE source = ?1 AND number = ?2 AND datetime = ?3",
+        params![source, dive.number, datetime],
+    )?;
+
+    let device_id = match dive.computer {
+        Some(ref dc) => {
+            let serial = dc.serial.as_deref().unwrap_or("");
+            tx.execute(
+                "INSERT OR IGNORE INTO devices (model, serial) VALUES (?1, ?2)",
+                params![dc.model, serial],
+            )?;
+            Some(tx.query_row(
+                "SELECT id FROM devices WHERE model = ?1 AND serial = ?2",
+                params![dc.model, serial],
+                |row| row.get::<_, i64>(0),
+            )?)
+        }
+        None => None,
+    };
+    let site_id = match dive.site {
+        Some(ref name) => {
+            let country = dive.country.as_deref().unwrap_or("");
+            tx.execute(
+                "INSERT OR IGNORE INTO sites (name, country) VALUES (?1, ?2)",
+                params![name, country],
+            )?;
+            Some(tx.query_row(
+                "SELECT id FROM sites WHERE name = ?1 AND country = ?2",
+                params![name, country],
+                |row| row.get::<_, i64>(0),
+            )?)
+        }
+        None => None,
+    };
+
+    let tanks = (!dive.tanks.is_empty())
+        .then(|| serde_json::to_string(&dive.tanks))
+        .transpose()?;
+    let apneas = (!dive.apneas.is_empty())
+        .then(|| serde_json::to_string(&dive.apneas))
+        .transpose()?;
+    let details = (!dive.details.is_empty())
+        .then(|| serde_json::to_string(&dive.details))
+        .transpose()?;
+    tx.execute(
+        "INSERT INTO dives (number, datetime, start_time, timezone, device_id, duration_seconds,
+             max_depth_m, min_temp_c, max_temp_c, dive_mode, site_id, country, buddy, source,
+             tanks, apneas, notes, details)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+        params![
+            dive.number,
+            datetime,
+            dive.start_time.map(format_datetime),
+            dive.timezone.map(|tz| tz.to_string()),
+            device_id,
+            dive.duration_seconds,
+            dive.max_depth_m,
+            dive.min_temp_c,
+            dive.max_temp_c,
+            to_text(&dive.dive_mode)?,
+            site_id,
+            dive.country,
+            dive.buddy,
+            source,
+            tanks,
+            apneas,
+            dive.notes,
+            details,
+        ],
+    )?;
+    let dive_id = tx.last_insert_rowid();
+
+    let mut insert_gas =
+        tx.prepare_cached("INSERT INTO gas_mixes (dive_id, idx, o2) VALUES (?1, ?2, ?3)")?;
+    for (i, gas) in dive.gas_mixes.iter().enumerate() {
+        insert_gas.execute(params![dive_id, i as i64, gas.o2])?;
+    }
+    let mut insert_sample = tx.prepare_cached(
+        "INSERT INTO samples (dive_id, time_s, depth_m, temp_c, pressure_bar)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for s in &dive.samples {
+        insert_sample.execute(params![dive_id, s.time_s, s.depth_m, s.temp_c, s.pressure_bar])?;
+    }
+    Ok(())
+}
+
+fn load_dives(conn: &Connection) -> Result<Vec<DiveLog>> {
+    let mut stmt = conn.prepare(
+        "SELECT d.id, d.number, d.datetime, d.start_time, d.timezone, dv.model, dv.serial,
+                d.duration_seconds, d.max_depth_m, d.min_temp_c, d.max_temp_c, d.dive_mode,
+                s.name, d.country, d.buddy, d.source, d.tanks, d.apneas, d.notes,
+                d.details
+         FROM dives d
+         LEFT JOIN devices dv ON dv.id = d.device_id
+         LEFT JOIN sites s ON s.id = d.site_id
+         ORDER BY d.datetime, d.number",
+    )?;
+    let mut gas_stmt = conn.prepare("SELECT o2 FROM gas_mixes WHERE dive_id = ?1 ORDER BY idx")?;
+    let mut sample_stmt = conn.prepare(
+        "SELECT time_s, depth_m, temp_c, pressure_bar FROM samples
+         WHERE dive_id = ?1 ORDER BY rowid",
+    )?;
+
+    let mut rows = stmt.query([])?;
+    let mut dives = Vec::new();
+    while let Some(row) = rows.next()? {
+        let id: i64 = row.get(0)?;
+        let model: Option<String> = row.get(5)?;
+        let serial: Option<String> = row.get(6)?;
+        let timezone: Option<String> = row.get(4)?;
+        let source: String = row.get(15)?;
+        let tanks: Option<String> = row.get(16)?;
+        let apneas: Option<String> = row.get(17)?;
+        let details: Option<String> = row.get(19)?;
+
+        let gas_mixes = gas_stmt
+            .query_map([id], |r| Ok(GasMix { o2: r.get(0)? }))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let samples = sample_stmt
+            .query_map([id], |r| {
+                Ok(Sample {
+                    time_s: r.get(0)?,
+                    depth_m: r.get(1)?,
+                    temp_c: r.get(2)?,
+                    pressure_bar: r.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        dives.push(DiveLog {
+            number: row.get(1)?,
+            datetime: parse_datetime(&row.get::<_, String>(2)?)?,
+            start_time: row
+                .get::<_, Option<String>>(3)?
+                .map(|t| parse_datetime(&t))
+                .transpose()?,
+            timezone: timezone
+                .map(|tz| tz.parse().map_err(anyhow::Error::msg))
+                .transpose()?,
+            computer: model.map(|model| DiveComputer {
+                model,
+                serial: serial.filter(|s| !s.is_empty()),
+            }),
+            duration_seconds: row.get(7)?,
+            max_depth_m: row.get(8)?,
+            min_temp_c: row.get(9)?,
+            max_temp_c: row.get(10)?,
+            dive_mode: from_text(row.get(11)?)?,
+            gas_mixes,
+            tanks: tanks.map(|t| serde_json::from_str(&t)).transpose()?.unwrap_or_default(),
+            samples,
+            apneas: apneas.map(|a| serde_json::from_str(&a)).transpose()?.unwrap_or_default(),
+            site: row.get(12)?,
+            country: row.get(13)?,
+            buddy: row.get(14)?,
+            notes: row.get(18)?,
+            details: details.map(|d| serde_json::from_str(&d)).transpose()?.unwrap_or_default(),
+            source: (!source.is_empty()).then_some(source),
+        });
+    }
+    Ok(dives)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty directory for one test's logbook files.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sirius-store-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn sqlite_backup_includes_uncheckpointed_commits() {
+        let dir = temp_dir("backup");
+        let store = Store::Sqlite(dir.join("dives.db"));
+        store.save_dives(&[DiveLog::sample()]).unwrap();
+
+        // An open reader keeps the next commit in the WAL file
+        let reader = open(store.path()).unwrap();
+        store.save_dives(&[DiveLog::sample_at(13, "2025-03-05 09:00")]).unwrap();
+        assert!(dir.join("dives.db-wal").exists());
+
+        let backup = Store::Sqlite(store.backup().unwrap());
+        let numbers: Vec<u32> = backup.load().unwrap().dives.iter().map(|d| d.number).collect();
+        assert_eq!(numbers, [12, 13]);
+
+        drop(reader);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// A dive using every optional field, including the ones kept as JSON
+    /// text or as '' for "missing" in the database.
+    fn full_dive() -> DiveLog {
+        let apnea = |number, start_s| Apnea {
+            number,
+            start_s,
+            duration_s: 8,
+            max_depth_m: 12.3,
+            descent_speed: Some(1.5),
+            ascent_speed: None,
+            surface_s: (number == 1).then_some(2),
+        };
+        let mut dive = DiveLog {
+            start_time: Some(
+                NaiveDateTime::parse_from_str("2025-03-04T10:15:37", DATETIME_FORMAT).unwrap(),
+            ),
+            timezone: Some("Asia/Makassar".parse().unwrap()),
+            min_temp_c: Some(26.5),
+            max_temp_c: Some(27.0),
+            apneas: vec![apnea(1, 0), apnea(2, 10)],
+            site: Some("Blue Corner".to_string()),
+            country: Some("Palau".to_string()),
+            buddy: Some("Jane Doe, John Roe".to_string()),
+            notes: Some("Mantas\nStrong current".to_string()),
+            details: DiveDetails {
+                dive_type: Some("Fun dive".to_string()),
+                visibility_m: Some(20.0),
+                weight_kg: Some(4.5),
+                rating: Some(4),
+                ..DiveDetails::default()
+            },
+            source: Some("old-logbook.uddf".to_string()),
+            ..DiveLog::sample()
+        };
+        dive.tanks[0].begin_pressure_bar = Some(200.0);
+        dive.tanks[0].end_pressure_bar = Some(60.0);
+        dive.samples[1].pressure_bar = Some(195.0);
+        dive
+    }
+
+    #[test]
+    fn sqlite_round_trip() {
+        let dir = temp_dir("round-trip");
+        let store = Store::Sqlite(dir.join("dives.db"));
+        // No serial and no source: stored as '' and read back as missing
+        let mut bare = DiveLog::sample_at(13, "2025-03-05 09:00");
+        bare.computer.as_mut().unwrap().serial = None;
+        let dives = vec![full_dive(), bare];
+        store.save_dives(&dives).unwrap();
+
+        let loaded = store.load().unwrap().dives;
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&dives).unwrap()
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stores_agree_on_dive_order() {
+        // Local clock order is the reverse of the UTC order
+        let east = DiveLog {
+            timezone: Some("+08:00".parse().unwrap()),
+            ..DiveLog::sample_at(1, "2025-03-04 10:00")
+        };
+        let west = DiveLog {
+            timezone: Some("-05:00".parse().unwrap()),
+            ..DiveLog::sample_at(2, "2025-03-04 08:00")
+        };
+
+        let dir = temp_dir("order");
+        for store in [Store::Json(dir.join("dives.json")), Store::Sqlite(dir.join("dives.db"))] {
+            store.save_dives(&[west.clone(), east.clone()]).unwrap();
+            let numbers: Vec<u32> = store.load().unwrap().dives.iter().map(|d| d.number).collect();
+            assert_eq!(numbers, [1, 2], "{}", store.path().display());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,544 @@
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::{bail, Context, Result};
+use chrono::{NaiveDate, NaiveTime, Timelike};
+
+use crate::freedive;
+use crate::types::*;
+use crate::xml::{child, child_text, XmlWriter};
+
+/// Subsurface taxonomy category for the country of a dive site.
+const GEO_CAT_COUNTRY: &str = "2";
+
+/// Sample pressure attributes for the first, second... cylinder.
+const PRESSURE_ATTRS: [&str; 5] = ["pressure", "pressure1", "pressure2", "pressure3", "pressure4"];
+
+/// 32-bit FNV-1a, used for the stable hex ids Subsurface expects for dive
+/// sites and dive computers.
+fn fnv1a(text: &str) -> u32 {
+    text.bytes().fold(0x811c_9dc5, |hash, b| {
+        (hash ^ b as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+fn site_uuid(site: &str, country: Option<&str>) -> String {
+    format!("{:08x}", fnv1a(&format!("{}\0{}", site, country.unwrap_or(""))))
+}
+
+fn device_id(serial: &str) -> String {
+    format!("{:08x}", fnv1a(serial))
+}
+
+fn minutes(seconds: u32) -> String {
+    format!("{}:{:02} min", seconds / 60, seconds % 60)
+}
+
+/// Build a Subsurface XML logbook (`.ssrf`) containing all `dives`.
+pub fn dives_to_ssrf(dives: &[DiveLog]) -> String {
+    let computers: BTreeSet<_> = dives.iter().filter_map(|d| d.computer.as_ref()).collect();
+    let sites: BTreeSet<(&str, Option<&str>)> = dives
+        .iter()
+        .filter_map(|d| Some((d.site.as_deref()?, d.country.as_deref())))
+        .collect();
+
+    let mut xml = XmlWriter::new();
+    xml.open("divelog", &[("program", "subsurface"), ("version", "3")]);
+
+    xml.open("settings", &[]);
+    for dc in &computers {
+        match dc.serial {
+            Some(ref serial) => xml.empty(
+                "divecomputerid",
+                &[
+                    ("model", &dc.model),
+                    ("deviceid", &device_id(serial)),
+                    ("serial", serial),
+                ],
+            ),
+            None => xml.empty("divecomputerid", &[("model", &dc.model)]),
+        }
+    }
+    xml.close();
+
+    xml.open("divesites", &[]);
+    for &(site, country) in &sites {
+        xml.open(
+            "site",
+            &[("uuid", &site_uuid(site, country)), ("name", site)],
+        );
+        if let Some(country) = country {
+            xml.empty(
+                "geo",
+                &[("cat", GEO_CAT_COUNTRY), ("origin", "0"), ("value", country)],
+            );
+        }
+        xml.close();
+    }
+    xml.close();
+
+    xml.open("dives", &[]);
+    for dive in dives {
+        write_dive(&mut xml, dive);
+    }
+    xml.close();
+
+    xml.close();
+    xml.finish()
+}
+
+fn write_dive(xml: &mut XmlWriter, dive: &DiveLog) {
+    let (start, _) = dive.start();
+    let number = dive.number.to_string();
+    let date = start.format("%Y-%m-%d").to_string();
+    let time = start.format("%H:%M:%S").to_string();
+    let duration = minutes(dive.duration_seconds);
+    let mut attrs = vec![
+        ("number", number.as_str()),
+        ("date", date.as_str()),
+        ("time", time.as_str()),
+        ("duration", duration.as_str()),
+    ];
+    let site_id = dive
+        .site
+        .as_deref()
+        .map(|site| site_uuid(site, dive.country.as_deref()));
+    if let Some(ref id) = site_id {
+        attrs.push(("divesiteid", id));
+    }
+    let rating = dive.details.rating.map(|r| r.to_string());
+    if let Some(ref rating) = rating {
+        attrs.push(("rating", rating));
+    }
+    let details = &dive.details;
+    let tags: Vec<&str> = [
+        details.dive_type.as_deref(),
+        details.entry.as_deref(),
+        details.water_body.as_deref(),
+        details.water_type.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let tags = tags.join(", ");
+    if !tags.is_empty() {
+        attrs.push(("tags", &tags));
+    }
+    xml.open("dive", &attrs);
+
+    let buddies = dive.buddies();
+    if !buddies.is_empty() {
+        xml.leaf("buddy", &[], &buddies.join(", "));
+    }
+    if let Some(ref notes) = dive.notes {
+        xml.leaf("notes", &[], notes);
+    }
+
+    // Cylinders: one per tank slot, or one per gas mix without transmitters.
+    // Sample pressures refer to cylinders by position.
+    let pressures = dive.sample_pressures(PressureFill::None);
+    let mut tank_slots: BTreeSet<u8> = dive.tanks.iter().map(|t| t.index).collect();
+    tank_slots.extend(pressures.iter().map(|(tank, _)| *tank));
+    if tank_slots.is_empty() {
+        for gas in &dive.gas_mixes {
+            write_cylinder(xml, Some(gas.o2), None, None);
+        }
+    } else {
+        let series = dive.pressure_series();
+        for &index in &tank_slots {
+            let tank = dive.tanks.iter().find(|t| t.index == index);
+            let o2 = tank
+                .and_then(|t| t.gas_mix)
+                .or((dive.gas_mixes.len() == 1).then_some(0))
+                .and_then(|g| dive.gas_mixes.get(g))
+                .map(|g| g.o2);
+            let points = series
+                .iter()
+                .find(|(t, _)| *t == index)
+                .map(|(_, readings)| dive.pressure_points(index, readings))
+                .unwrap_or_default();
+            let start = tank
+                .and_then(|t| t.begin_pressure_bar)
+                .or(points.first().map(|p| p.1));
+            let end = tank
+                .and_then(|t| t.end_pressure_bar)
+                .or(points.last().map(|p| p.1));
+            write_cylinder(xml, o2, start, end);
+        }
+    }
+    if let Some(weight) = details.weight_kg {
+        xml.empty(
+            "weightsystem",
+            &[("weight", &format!("{:.1} kg", weight)), ("description", "weight")],
+        );
+    }
+
+    let mut dc_attrs: Vec<(&str, String)> = Vec::new();
+    if let Some(ref dc) = dive.computer {
+        dc_attrs.push(("model", dc.model.clone()));
+        if let Some(ref serial) = dc.serial {
+            dc_attrs.push(("deviceid", device_id(serial)));
+        }
+    }
+    if dive.dive_mode == DiveMode::Freedive {
+        dc_attrs.push(("dctype", "Freedive".to_string()));
+    }
+    let dc_attrs: Vec<(&str, &str)> = dc_attrs.iter().map(|(k, v)| (*k, v.as_str())).collect();
+    xml.open("divecomputer", &dc_attrs);
+
+    xml.empty("depth", &[("max", &format!("{:.1} m", dive.max_depth_m))]);
+    let mut temperature = Vec::new();
+    if let Some(air) = details.air_temp_c {
+        temperature.push(("air", format!("{:.1} C", air)));
+    }
+    if let Some((min, _)) = dive.temp_range() {
+        temperature.push(("water", format!("{:.1} C", min)));
+    }
+    if !temperature.is_empty() {
+        let attrs: Vec<(&str, &str)> = temperature.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        xml.empty("temperature", &attrs);
+    }
+    if let Some(serial) = dive.computer.as_ref().and_then(|dc| dc.serial.as_deref()) {
+        xml.empty("extradata", &[("key", "Serial"), ("value", serial)]);
+    }
+
+    for (i, sample) in dive.samples.iter().enumerate() {
+        let mut sample_attrs = vec![
+            ("time", minutes(sample.time_s)),
+            ("depth", format!("{:.1} m", sample.depth_m)),
+        ];
+        if let Some(temp) = sample.temp_c {
+            sample_attrs.push(("temp", format!("{:.1} C", temp)));
+        }
+        for (tank, values) in &pressures {
+            let Some(bar) = values[i] else { continue };
+            let cylinder = tank_slots.iter().position(|t| t == tank).unwrap_or(0);
+            if let Some(key) = PRESSURE_ATTRS.get(cylinder) {
+                sample_attrs.push((key, format!("{:.1} bar", bar)));
+            }
+        }
+        let sample_attrs: Vec<(&str, &str)> =
+            sample_attrs.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        xml.empty("sample", &sample_attrs);
+    }
+
+    xml.close();
+    xml.close();
+}
+
+fn write_cylinder(xml: &mut XmlWriter, o2: Option<u8>, start: Option<f64>, end: Option<f64>) {
+    let mut attrs = Vec::new();
+    // Subsurface leaves air implicit
+    if let Some(o2) = o2.filter(|&o2| o2 != 21) {
+        attrs.push(("o2", format!("{}.0%", o2)));
+    }
+    if let Some(start) = start {
+        attrs.push(("start", format!("{:.1} bar", start)));
+    }
+    if let Some(end) = end {
+        attrs.push(("end", format!("{:.1} bar", end)));
+    }
+    let attrs: Vec<(&str, &str)> = attrs.iter().map(|(k, v)| (*k, v.as_str())).collect();
+    xml.empty("cylinder", &attrs);
+}
+
+/// Parse a Subsurface value with unit, e.g. "12.3 m", "27.0 C", "200.0 bar",
+/// "32.0%", converting imperial units to metric.
+fn metric(text: &str) -> Option<f64> {
+    let mut parts = text.split_whitespace();
+    let value: f64 = parts.next()?.trim_end_matches('%').parse().ok()?;
+    Some(match parts.next() {
+        Some("ft") => value * 0.3048,
+        Some("F") => (value - 32.0) * 5.0 / 9.0,
+        Some("psi") => value / 14.503_773_8,
+        Some("lbs") => value * 0.453_592_37,
+        _ => value,
+    })
+}
+
+/// Parse a Subsurface duration, "45:30 min" or "1:05:30".
+fn seconds(text: &str) -> Option<u32> {
+    let clock = text.split_whitespace().next()?;
+    clock
+        .split(':')
+        .try_fold(0u32, |acc, part| Some(acc * 60 + part.parse::<u32>().ok()?))
+}
+
+/// Read the dives of a Subsurface XML logbook into `DiveLog`s.
+pub fn parse_ssrf(text: &str) -> Result<Vec<DiveLog>> {
+    let doc = roxmltree::Document::parse(text).context("Invalid XML")?;
+    let root = doc.root_element();
+    if !root.has_tag_name("divelog") {
+        bail!(
+            "Not a Subsurface logbook (root element <{}>)",
+            root.tag_name().name()
+        );
+    }
+
+    let sites: HashMap<&str, (Option<&str>, Option<&str>)> = root
+        .descendants()
+        .filter(|n| n.has_tag_name("site"))
+        .filter_map(|site| {
+            let country = site
+                .children()
+                .find(|g| g.has_tag_name("geo") && g.attribute("cat") == Some(GEO_CAT_COUNTRY))
+                .and_then(|g| g.attribute("value"));
+            Some((site.attribute("uuid")?.trim(), (site.attribute("name"), country)))
+        })
+        .collect();
+    let serials: HashMap<&str, &str> = root
+        .descendants()
+        .filter(|n| n.has_tag_name("divecomputerid"))
+        .filter_map(|dc| Some((dc.attribute("deviceid")?, dc.attribute("serial")?)))
+        .collect();
+
+    let mut dives = Vec::new();
+    let dive_nodes = child(root, "dives")
+        .into_iter()
+        .flat_map(|d| d.descendants())
+        .filter(|n| n.has_tag_name("dive"));
+    for (i, node) in dive_nodes.enumerate() {
+        let date = node
+            .attribute("date")
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+        let time = node
+            .attribute("time")
+            .and_then(|t| NaiveTime::parse_from_str(t, "%H:%M:%S").ok());
+        let (Some(date), Some(time)) = (date, time) else {
+            eprintln!(
+                "Warning: skipping Subsurface dive {} without a valid date",
+                node.attribute("number").unwrap_or("?")
+            );
+            continue;
+        };
+        let start = date.and_time(time);
+
+        // Site by id, or the inline <location> of older logbooks
+        let (site, country) = match node.attribute("divesiteid") {
+            Some(id) => sites.get(id.trim()).copied().unwrap_or_default(),
+            None => (child_text(node, "location"), None),
+        };
+
+        // Cylinders in order; sample pressures refer to them by position
+        let mut gas_mixes: Vec<GasMix> = Vec::new();
+        let mut tanks: Vec<Tank> = Vec::new();
+        for (index, cylinder) in node
+            .children()
+            .filter(|n| n.has_tag_name("cylinder"))
+            .enumerate()
+        {
+            let o2 = cylinder
+                .attribute("o2")
+                .and_then(metric)
+                .map_or(21, |o2| o2.round() as u8);
+            let gas_mix = match gas_mixes.iter().position(|g| g.o2 == o2) {
+                Some(g) => g,
+                None => {
+                    gas_mixes.push(GasMix { o2 });
+                    gas_mixes.len() - 1
+                }
+            };
+            tanks.push(Tank {
+                index: index as u8,
+                gas_mix: Some(gas_mix),
+                begin_pressure_bar: cylinder.attribute("start").and_then(metric),
+                end_pressure_bar: cylinder.attribute("end").and_then(metric),
+                readings: Vec::new(),
+            });
+        }
+
+        let dc = child(node, "divecomputer");
+        let mut samples = Vec::new();
+        for sample in dc
+            .into_iter()
+            .flat_map(|dc| dc.children())
+            .filter(|n| n.has_tag_name("sample"))
+        {
+            let time = sample.attribute("time").and_then(seconds);
+            let depth = sample.attribute("depth").and_then(metric);
+            let (Some(time_s), Some(depth_m)) = (time, depth) else {
+                continue;
+            };
+            for (cylinder, attr) in PRESSURE_ATTRS.iter().enumerate() {
+                let Some(bar) = sample.attribute(*attr).and_then(metric) else {
+                    continue;
+                };
+                if cylinder >= tanks.len() {
+                    continue;
+                }
+                tanks[cylinder].readings.push(PressureReading {
+                    time_s,
+                    pressure_bar: bar,
+                });
+            }
+            samples.push(Sample {
+                time_s,
+                depth_m,
+                temp_c: sample.attribute("temp").and_then(metric),
+                pressure_bar: None,
+            });
+        }
+        tanks.retain(|t| {
+            t.begin_pressure_bar.is_some() || t.end_pressure_bar.is_some() || !t.readings.is_empty()
+        });
+
+        let computer = dc.and_then(|dc| {
+            let model = dc.attribute("model")?;
+            let serial = dc
+                .children()
+                .find(|e| e.has_tag_name("extradata") && e.attribute("key") == Some("Serial"))
+                .and_then(|e| e.attribute("value"))
+                .or(dc.attribute("deviceid").and_then(|id| serials.get(id).copied()));
+            Some(DiveComputer {
+                model: model.to_string(),
+                serial: serial.map(str::to_string),
+            })
+        });
+
+        let dive_mode = if dc.and_then(|dc| dc.attribute("dctype")) == Some("Freedive") {
+            DiveMode::Freedive
+        } else if gas_mixes.iter().any(|g| g.o2 > 21) {
+            DiveMode::Nitrox
+        } else {
+            DiveMode::Air
+        };
+        if gas_mixes.is_empty() {
+            gas_mixes.push(GasMix { o2: 21 });
+        }
+
+        let max_depth_m = dc
+            .and_then(|dc| child(dc, "depth"))
+            .and_then(|d| d.attribute("max"))
+            .and_then(metric)
+            .unwrap_or_else(|| samples.iter().map(|s| s.depth_m).fold(0.0, f64::max));
+        let temperature = dc
+            .and_then(|dc| child(dc, "temperature"))
+            .or(child(node, "temperature"));
+        let water_temp = temperature.and_then(|t| t.attribute("water")).and_then(metric);
+        let weights: Vec<f64> = node
+            .children()
+            .filter(|n| n.has_tag_name("weightsystem"))
+            .filter_map(|w| w.attribute("weight").and_then(metric))
+            .collect();
+
+        let mut dive = DiveLog {
+            number: node
+                .attribute("number")
+                .and_then(|n| n.parse().ok())
+                .unwrap_or(i as u32 + 1),
+            datetime: start.with_second(0).unwrap_or(start),
+            start_time: (start.second() != 0).then_some(start),
+            timezone: None,
+            computer,
+            duration_seconds: node
+                .attribute("duration")
+                .and_then(seconds)
+                .unwrap_or_else(|| samples.last().map_or(0, |s| s.time_s)),
+            max_depth_m,
+            min_temp_c: water_temp,
+            max_temp_c: None,
+            dive_mode,
+            gas_mixes,
+            tanks,
+            samples,
+            apneas: Vec::new(),
+            site: site.map(str::to_string),
+            country: country.map(str::to_string),
+            buddy: child_text(node, "buddy").map(str::to_string),
+            notes: child_text(node, "notes").map(str::to_string),
+            details: DiveDetails {
+                air_temp_c: temperature.and_then(|t| t.attribute("air")).and_then(metric),
+                weight_kg: (!weights.is_empty()).then(|| weights.iter().sum()),
+                rating: node
+                    .attribute("rating")
+                    .and_then(|r| r.parse().ok())
+                    .filter(|r| (1..=5).contains(r)),
+                ..DiveDetails::default()
+            },
+            source: None,
+        };
+        if dive.dive_mode == DiveMode::Freedive {
+            dive.apneas = freedive::split_apneas(&dive.samples);
+        }
+        dives.push(dive);
+    }
+
+    Ok(dives)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Imperial-unit logbook: a two-cylinder nitrox dive at a `divesiteid`
+    /// site, and an older dive with an inline `<location>`.
+    const IMPERIAL: &str = include_str!("../testdata/imperial.ssrf");
+
+    fn round1(value: f64) -> f64 {
+        (value * 10.0).round() / 10.0
+    }
+
+    #[test]
+    fn imperial_units_are_converted() {
+        let dives = parse_ssrf(IMPERIAL).unwrap();
+        let dive = &dives[0];
+        assert_eq!(dive.number, 41);
+        let start = dive.start().0.format("%Y-%m-%d %H:%M:%S").to_string();
+        assert_eq!(start, "2024-08-10 09:12:30");
+        assert_eq!(dive.duration_seconds, 180);
+        assert_eq!(round1(dive.max_depth_m), 18.3);
+        assert_eq!(dive.min_temp_c.map(round1), Some(15.0));
+        assert_eq!(dive.details.air_temp_c.map(round1), Some(30.0));
+        assert_eq!(dive.details.weight_kg.map(round1), Some(5.4));
+        assert_eq!(dive.details.rating, Some(4));
+
+        let depths: Vec<f64> = dive.samples.iter().map(|s| round1(s.depth_m)).collect();
+        assert_eq!(depths, [0.0, 18.3, 10.1, 0.0]);
+        let temps: Vec<Option<f64>> = dive.samples.iter().map(|s| s.temp_c.map(round1)).collect();
+        assert_eq!(temps, [Some(20.0), Some(15.0), None, None]);
+    }
+
+    #[test]
+    fn cylinders_keep_their_gas_and_pressures() {
+        let dive = &parse_ssrf(IMPERIAL).unwrap()[0];
+        assert_eq!(dive.dive_mode, DiveMode::Nitrox);
+        let o2: Vec<u8> = dive.gas_mixes.iter().map(|g| g.o2).collect();
+        assert_eq!(o2, [32, 21]);
+
+        let tanks: Vec<_> = dive
+            .tanks
+            .iter()
+            .map(|t| {
+                let readings: Vec<_> =
+                    t.readings.iter().map(|r| (r.time_s, round1(r.pressure_bar))).collect();
+                let begin = t.begin_pressure_bar.map(round1);
+                (t.index, t.gas_mix, begin, t.end_pressure_bar.map(round1), readings)
+            })
+            .collect();
+        assert_eq!(
+            tanks,
+            [
+                (0, Some(0), Some(206.8), Some(68.9), vec![(0, 206.8), (60, 137.9), (180, 68.9)]),
+                (1, Some(1), Some(206.8), Some(172.4), vec![(0, 206.8), (120, 172.4)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn site_from_divesiteid_or_location() {
+        let dives = parse_ssrf(IMPERIAL).unwrap();
+        assert_eq!(dives.len(), 2);
+        assert_eq!(dives[0].site.as_deref(), Some("Casino Point"));
+        assert_eq!(dives[0].country.as_deref(), Some("United States"));
+        assert_eq!(dives[0].buddy.as_deref(), Some("Sam"));
+        // Serial resolved through the settings' divecomputerid
+        let dc = dives[0].computer.as_ref().unwrap();
+        assert_eq!(dc.model, "Shearwater Perdix");
+        assert_eq!(dc.serial.as_deref(), Some("PX-4711"));
+
+        assert_eq!(dives[1].number, 7);
+        assert_eq!(dives[1].site.as_deref(), Some("Blue Hole"));
+        assert_eq!(dives[1].country, None);
+        assert_eq!(dives[1].max_depth_m, 18.5);
+        assert_eq!(dives[1].gas_mixes.iter().map(|g| g.o2).collect::<Vec<_>>(), [21]);
+    }
+}
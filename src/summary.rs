@@ -0,0 +1,172 @@
+use clap::ValueEnum;
+
+use crate::types::DiveLog;
+
+/// A column of the logbook summary CSV.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SummaryColumn {
+    Number,
+    Date,
+    Time,
+    /// Duration as m:ss
+    Duration,
+    MaxDepth,
+    AvgDepth,
+    MinTemp,
+    MaxTemp,
+    StartPressure,
+    EndPressure,
+    Gas,
+    Mode,
+    Site,
+    Country,
+    Buddy,
+    Notes,
+    DiveType,
+    WaterBody,
+    WaterType,
+    Visibility,
+    Weather,
+    AirTemp,
+    Entry,
+    Weight,
+    /// Rating from 1 to 5
+    Rating,
+}
+
+impl SummaryColumn {
+    pub const ALL: [SummaryColumn; 25] = [
+        SummaryColumn::Number,
+        SummaryColumn::Date,
+        SummaryColumn::Time,
+        SummaryColumn::Duration,
+        SummaryColumn::MaxDepth,
+        SummaryColumn::AvgDepth,
+        SummaryColumn::MinTemp,
+        SummaryColumn::MaxTemp,
+        SummaryColumn::StartPressure,
+        SummaryColumn::EndPressure,
+        SummaryColumn::Gas,
+        SummaryColumn::Mode,
+        SummaryColumn::Site,
+        SummaryColumn::Country,
+        SummaryColumn::Buddy,
+        SummaryColumn::Notes,
+        SummaryColumn::DiveType,
+        SummaryColumn::WaterBody,
+        SummaryColumn::WaterType,
+        SummaryColumn::Visibility,
+        SummaryColumn::Weather,
+        SummaryColumn::AirTemp,
+        SummaryColumn::Entry,
+        SummaryColumn::Weight,
+        SummaryColumn::Rating,
+    ];
+
+    fn header(self) -> &'static str {
+        match self {
+            SummaryColumn::Number => "number",
+            SummaryColumn::Date => "date",
+            SummaryColumn::Time => "time",
+            SummaryColumn::Duration => "duration",
+            SummaryColumn::MaxDepth => "max_depth_m",
+            SummaryColumn::AvgDepth => "avg_depth_m",
+            SummaryColumn::MinTemp => "min_temp_c",
+            SummaryColumn::MaxTemp => "max_temp_c",
+            SummaryColumn::StartPressure => "start_pressure_bar",
+            SummaryColumn::EndPressure => "end_pressure_bar",
+            SummaryColumn::Gas => "gas",
+            SummaryColumn::Mode => "mode",
+            SummaryColumn::Site => "site",
+            SummaryColumn::Country => "country",
+            SummaryColumn::Buddy => "buddy",
+            SummaryColumn::Notes => "notes",
+            SummaryColumn::DiveType => "dive_type",
+            SummaryColumn::WaterBody => "water_body",
+            SummaryColumn::WaterType => "water_type",
+            SummaryColumn::Visibility => "visibility_m",
+            SummaryColumn::Weather => "weather",
+            SummaryColumn::AirTemp => "air_temp_c",
+            SummaryColumn::Entry => "entry",
+            SummaryColumn::Weight => "weight_kg",
+            SummaryColumn::Rating => "rating",
+        }
+    }
+
+    fn value(self, dive: &DiveLog) -> String {
+        let (start, _) = dive.start();
+        let main_tank = dive.tank_pressures().first().map(|&(_, start, end)| (start, end));
+        let one_decimal = |v: Option<f64>| v.map(|v| format!("{:.1}", v)).unwrap_or_default();
+        let details = &dive.details;
+        match self {
+            SummaryColumn::Number => dive.number.to_string(),
+            SummaryColumn::Date => start.format("%Y-%m-%d").to_string(),
+            SummaryColumn::Time => start.format("%H:%M:%S").to_string(),
+            SummaryColumn::Duration => format!(
+                "{}:{:02}",
+                dive.duration_seconds / 60,
+                dive.duration_seconds % 60
+            ),
+            SummaryColumn::MaxDepth => format!("{:.1}", dive.max_depth_m),
+            SummaryColumn::AvgDepth => one_decimal(dive.avg_depth()),
+            SummaryColumn::MinTemp => one_decimal(dive.temp_range().map(|(min, _)| min)),
+            SummaryColumn::MaxTemp => one_decimal(dive.temp_range().map(|(_, max)| max)),
+            SummaryColumn::StartPressure => {
+                main_tank.map(|(p, _)| format!("{:.0}", p)).unwrap_or_default()
+            }
+            SummaryColumn::EndPressure => {
+                main_tank.map(|(_, p)| format!("{:.0}", p)).unwrap_or_default()
+            }
+            SummaryColumn::Gas => dive
+                .gas_mixes
+                .iter()
+                .map(|g| g.name())
+                .collect::<Vec<_>>()
+                .join("/"),
+            SummaryColumn::Mode => format!("{:?}", dive.dive_mode).to_lowercase(),
+            SummaryColumn::Site => dive.site.clone().unwrap_or_default(),
+            SummaryColumn::Country => dive.country.clone().unwrap_or_default(),
+            SummaryColumn::Buddy => dive.buddy.clone().unwrap_or_default(),
+            SummaryColumn::Notes => dive.notes.clone().unwrap_or_default(),
+            SummaryColumn::DiveType => details.dive_type.clone().unwrap_or_default(),
+            SummaryColumn::WaterBody => details.water_body.clone().unwrap_or_default(),
+            SummaryColumn::WaterType => details.water_type.clone().unwrap_or_default(),
+            SummaryColumn::Visibility => one_decimal(details.visibility_m),
+            SummaryColumn::Weather => details.weather.clone().unwrap_or_default(),
+            SummaryColumn::AirTemp => one_decimal(details.air_temp_c),
+            SummaryColumn::Entry => details.entry.clone().unwrap_or_default(),
+            SummaryColumn::Weight => one_decimal(details.weight_kg),
+            SummaryColumn::Rating => details.rating.map(|r| r.to_string()).unwrap_or_default(),
+        }
+    }
+}
+
+/// Quote a field if it contains the delimiter, a quote or a line break.
+fn csv_field(value: &str, delimiter: char) -> String {
+    if value.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Logbook summary: a header row and one row per dive with the chosen columns.
+pub fn dives_to_summary_csv(dives: &[DiveLog], columns: &[SummaryColumn], delimiter: char) -> String {
+    let separator = delimiter.to_string();
+    let mut csv = columns
+        .iter()
+        .map(|c| c.header())
+        .collect::<Vec<_>>()
+        .join(&separator);
+    csv.push('\n');
+
+    for dive in dives {
+        let row: Vec<String> = columns
+            .iter()
+            .map(|c| csv_field(&c.value(dive), delimiter))
+            .collect();
+        csv.push_str(&row.join(&separator));
+        csv.push('\n');
+    }
+    csv
+}
@@ -1,6 +1,4 @@
-use std::path::PathBuf;
-
-use anyhow::{Context, Result};
+use anyhow::Result;
 use crossterm::event::{self, Event, KeyCode, KeyEventKind};
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
 use ratatui::layout::{Constraint, Direction, Layout};
@@ -12,7 +10,9 @@ use ratatui::widgets::{
 };
 use ratatui::DefaultTerminal;
 
-use crate::types::{DiveData, DiveLog, DiveMode};
+use crate::freedive;
+use crate::store::Store;
+use crate::types::{DiveLog, DiveMode};
 
 struct App {
     dives: Vec<DiveLog>,
@@ -21,6 +21,8 @@ struct App {
     show_depth: bool,
     show_temp: bool,
     show_pressure: bool,
+    /// Draw tank pressure as lines through the readings (vs raw points)
+    interpolate_pressure: bool,
 }
 
 impl App {
@@ -36,6 +38,7 @@ impl App {
             show_depth: true,
             show_temp: true,
             show_pressure: true,
+            interpolate_pressure: true,
         }
     }
 
@@ -49,6 +52,7 @@ impl App {
             KeyCode::Char('d') => self.show_depth = !self.show_depth,
             KeyCode::Char('t') => self.show_temp = !self.show_temp,
             KeyCode::Char('p') => self.show_pressure = !self.show_pressure,
+            KeyCode::Char('i') => self.interpolate_pressure = !self.interpolate_pressure,
             KeyCode::Down | KeyCode::Char('j') => {
                 if let Some(i) = self.list_state.selected() {
                     if i + 1 < self.dives.len() {
@@ -63,35 +67,28 @@ impl App {
                     }
                 }
             }
-            KeyCode::Home => {
-                if !self.dives.is_empty() {
-                    self.list_state.select(Some(0));
-                }
+            KeyCode::Home if !self.dives.is_empty() => {
+                self.list_state.select(Some(0));
             }
-            KeyCode::End => {
-                if !self.dives.is_empty() {
-                    self.list_state.select(Some(self.dives.len() - 1));
-                }
+            KeyCode::End if !self.dives.is_empty() => {
+                self.list_state.select(Some(self.dives.len() - 1));
             }
             _ => {}
         }
     }
 }
 
-pub fn run(input: PathBuf) -> Result<()> {
-    let contents =
-        std::fs::read_to_string(&input).with_context(|| format!("Failed to read {}", input.display()))?;
-    let data: DiveData =
-        serde_json::from_str(&contents).with_context(|| format!("Failed to parse {}", input.display()))?;
+pub fn run(store: Store) -> Result<()> {
+    let data = store.load()?;
 
     if data.dives.is_empty() {
-        eprintln!("No dives found in {}", input.display());
+        eprintln!("No dives found in {}", store.path().display());
         return Ok(());
     }
 
-    // Sort dives by number descending (most recent first)
+    // Most recent first
     let mut dives = data.dives;
-    dives.sort_by(|a, b| b.number.cmp(&a.number));
+    dives.sort_by_key(|d| std::cmp::Reverse(d.start_utc()));
 
     let mut app = App::new(dives);
 
@@ -142,6 +139,9 @@ fn ui(frame: &mut ratatui::Frame, app: &mut App) {
     }
 }
 
+/// Pressure line colours, one per tank.
+const TANK_COLORS: [Color; 4] = [Color::Green, Color::Magenta, Color::Yellow, Color::LightBlue];
+
 fn mode_short(mode: &DiveMode) -> &'static str {
     match mode {
         DiveMode::Air => "Air",
@@ -187,21 +187,32 @@ fn render_dive_list(frame: &mut ratatui::Frame, app: &mut App, area: ratatui::la
 }
 
 fn render_detail_panel(frame: &mut ratatui::Frame, app: &mut App, area: ratatui::layout::Rect) {
+    let info = app
+        .selected_dive()
+        .map(|dive| dive_info_lines(dive, area.width))
+        .unwrap_or_default();
+    // Fit the info block to its rows (plus borders), keeping the chart usable
+    let info_height = (info.len() as u16 + 2).max(8);
     let right_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(8), Constraint::Min(10)])
+        .constraints([Constraint::Length(info_height), Constraint::Min(10)])
         .split(area);
 
-    render_dive_info(frame, app, right_chunks[0]);
+    render_dive_info(frame, info, right_chunks[0]);
     render_depth_chart(frame, app, right_chunks[1]);
 }
 
-fn render_dive_info(frame: &mut ratatui::Frame, app: &App, area: ratatui::layout::Rect) {
-    let dive = match app.selected_dive() {
-        Some(d) => d,
-        None => return,
-    };
+fn render_dive_info(frame: &mut ratatui::Frame, lines: Vec<Line<'static>>, area: ratatui::layout::Rect) {
+    let paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Dive Details "),
+    );
+
+    frame.render_widget(paragraph, area);
+}
 
+fn dive_info_lines(dive: &DiveLog, width: u16) -> Vec<Line<'static>> {
     let duration_min = dive.duration_seconds / 60;
     let duration_sec = dive.duration_seconds % 60;
 
@@ -212,29 +223,26 @@ fn render_dive_info(frame: &mut ratatui::Frame, app: &App, area: ratatui::layout
         .collect::<Vec<_>>()
         .join(", ");
 
-    // Temperature range from samples
-    let (temp_min, temp_max) = dive
-        .samples
-        .iter()
-        .filter_map(|s| s.temp_c)
-        .fold((f64::MAX, f64::MIN), |(min, max), t| {
-            (min.min(t), max.max(t))
-        });
+    // Temperature range from samples (or header)
+    let temp_range = dive.temp_range();
 
-    // Pressure: first and last non-None values
-    let pressure_start = dive.samples.iter().find_map(|s| s.pressure_bar);
-    let pressure_end = dive.samples.iter().rev().find_map(|s| s.pressure_bar);
+    // Pressure per tank: first and last readings
+    let pressures = dive.tank_pressures();
 
     // Two-column layout: left and right fields paired per row
     // col_w is the width of one column (half the inner area minus borders)
-    let inner_w = area.width.saturating_sub(2) as usize; // subtract borders
+    let inner_w = width.saturating_sub(2) as usize; // subtract borders
     let col_w = inner_w / 2;
 
     let mut left_col: Vec<String> = vec![
-        format!(
-            " Date:      {}",
-            dive.datetime.format("%Y-%m-%d %H:%M")
-        ),
+        match dive.timezone {
+            Some(tz) => format!(
+                " Date:      {} ({})",
+                dive.datetime.format("%Y-%m-%d %H:%M"),
+                dive.utc_offset().map_or_else(|| tz.to_string(), |o| o.to_string())
+            ),
+            None => format!(" Date:      {}", dive.datetime.format("%Y-%m-%d %H:%M")),
+        },
         format!(" Duration:  {:02}:{:02}", duration_min, duration_sec),
         format!(" Max depth: {:.1} m", dive.max_depth_m),
     ];
@@ -243,12 +251,50 @@ fn render_dive_info(frame: &mut ratatui::Frame, app: &App, area: ratatui::layout
         format!(" Gas:       {}", gas_str),
     ];
 
-    if temp_min != f64::MAX {
-        right_col.push(format!(" Temp:      {:.1} - {:.1} C", temp_min, temp_max));
+    if let Some((temp_min, temp_max)) = temp_range {
+        right_col.push(format!(" Temp:      {:.1} to {:.1} C", temp_min, temp_max));
     }
 
-    if let (Some(start), Some(end)) = (pressure_start, pressure_end) {
+    if let [(_, start, end)] = pressures[..] {
         right_col.push(format!(" Pressure:  {:.0} -> {:.0} bar", start, end));
+    } else {
+        for (tank, start, end) in &pressures {
+            right_col.push(format!(
+                " {:<10} {:.0} -> {:.0} bar",
+                format!("{}:", dive.tank_label(*tank)),
+                start,
+                end
+            ));
+        }
+    }
+
+    if dive.dive_mode == DiveMode::Freedive {
+        // Dives saved before apnea splitting only have the session profile
+        let apneas = if dive.apneas.is_empty() {
+            freedive::split_apneas(&dive.samples)
+        } else {
+            dive.apneas.clone()
+        };
+        if let Some(summary) = freedive::summarize(&apneas) {
+            let mmss = |s: u32| format!("{:02}:{:02}", s / 60, s % 60);
+            let speed = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{:.2}", v));
+            left_col.push(format!(
+                " Apneas:    {} ({} total)",
+                summary.apneas,
+                mmss(summary.total_apnea_s)
+            ));
+            left_col.push(format!(" Longest:   {}", mmss(summary.longest_s)));
+            right_col.push(format!(" Deepest:   {:.1} m", summary.deepest_m));
+            right_col.push(format!(
+                " Recovery:  {} avg",
+                summary.avg_surface_s.map_or("-".to_string(), mmss)
+            ));
+            right_col.push(format!(
+                " Speed:     {} down / {} up m/s",
+                speed(summary.avg_descent_speed),
+                speed(summary.avg_ascent_speed)
+            ));
+        }
     }
 
     if let Some(ref site) = dive.site {
@@ -260,6 +306,14 @@ fn render_dive_info(frame: &mut ratatui::Frame, app: &App, area: ratatui::layout
     if let Some(ref buddy) = dive.buddy {
         left_col.push(format!(" Buddy/Ctr: {}", buddy));
     }
+    for (i, (label, value)) in dive.details.fields().into_iter().enumerate() {
+        let field = format!(" {:<10} {}", format!("{}:", label), value);
+        if i % 2 == 0 {
+            left_col.push(field);
+        } else {
+            right_col.push(field);
+        }
+    }
 
     // Pad columns to same length
     let max_rows = left_col.len().max(right_col.len());
@@ -290,13 +344,19 @@ fn render_dive_info(frame: &mut ratatui::Frame, app: &App, area: ratatui::layout
         lines.push(Line::from(format!("{}{}", padded_left, r)));
     }
 
-    let paragraph = Paragraph::new(lines).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title(" Dive Details "),
-    );
+    if let Some(ref notes) = dive.notes {
+        let notes = notes.split_whitespace().collect::<Vec<_>>().join(" ");
+        let max = inner_w.saturating_sub(13);
+        let shown = if notes.chars().count() > max {
+            let cut: String = notes.chars().take(max.saturating_sub(1)).collect();
+            format!("{}…", cut)
+        } else {
+            notes
+        };
+        lines.push(Line::from(format!(" Notes:     {}", shown)));
+    }
 
-    frame.render_widget(paragraph, area);
+    lines
 }
 
 fn render_depth_chart(frame: &mut ratatui::Frame, app: &App, area: ratatui::layout::Rect) {
@@ -379,7 +439,7 @@ fn render_depth_chart(frame: &mut ratatui::Frame, app: &App, area: ratatui::layo
             })
             .collect();
 
-        temp_label = format!("Temp ({:.0}-{:.0}C)", tmin, tmax);
+        temp_label = format!("Temp ({:.1} to {:.1}C)", tmin, tmax);
 
         datasets.push(
             Dataset::default()
@@ -391,16 +451,32 @@ fn render_depth_chart(frame: &mut ratatui::Frame, app: &App, area: ratatui::layo
         );
     }
 
-    // Optional pressure overlay (drawn second = middle layer)
-    let has_pressure = app.show_pressure && dive.samples.iter().any(|s| s.pressure_bar.is_some());
-    let pressure_data: Vec<(f64, f64)>;
-    let pressure_label: String;
+    // Optional pressure overlay, one line per tank (drawn second = middle layer)
+    // Interpolated: lines through the readings and header begin/end pressures.
+    // Otherwise: the raw readings as points.
+    let series: Vec<(u8, Vec<(u32, f64)>)> = if app.show_pressure {
+        dive.pressure_series()
+            .into_iter()
+            .map(|(tank, readings)| {
+                let points = if app.interpolate_pressure {
+                    dive.pressure_points(tank, &readings)
+                } else {
+                    readings.iter().map(|r| (r.time_s, r.pressure_bar)).collect()
+                };
+                (tank, points)
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let pressure_data: Vec<Vec<(f64, f64)>>;
+    let pressure_labels: Vec<String>;
 
-    if has_pressure {
-        let (pmin, pmax) = dive
-            .samples
+    if !series.is_empty() {
+        // Shared scale so tanks can be compared against each other
+        let (pmin, pmax) = series
             .iter()
-            .filter_map(|s| s.pressure_bar)
+            .flat_map(|(_, points)| points.iter().map(|(_, p)| *p))
             .fold((f64::MAX, f64::MIN), |(min, max), p| {
                 (min.min(p), max.max(p))
             });
@@ -409,25 +485,43 @@ fn render_depth_chart(frame: &mut ratatui::Frame, app: &App, area: ratatui::layo
 
         // Normalize pressure to depth axis: map pressure range to [-depth_bound, 0]
         // Higher pressure → closer to 0 (top), lower pressure → closer to -depth_bound (bottom)
-        pressure_data = dive
-            .samples
+        pressure_data = series
             .iter()
-            .filter_map(|s| {
-                s.pressure_bar
-                    .map(|p| (s.time_s as f64 / 60.0, -((pmax - p) / pressure_range) * depth_bound))
+            .map(|(_, points)| {
+                points
+                    .iter()
+                    .map(|&(t, p)| (t as f64 / 60.0, -((pmax - p) / pressure_range) * depth_bound))
+                    .collect()
             })
             .collect();
 
-        pressure_label = format!("Press ({:.0}-{:.0}bar)", pmax, pmin);
-
-        datasets.push(
-            Dataset::default()
-                .name(pressure_label.as_str())
-                .marker(Marker::Braille)
-                .graph_type(GraphType::Line)
-                .style(Style::default().fg(Color::Green))
-                .data(&pressure_data),
-        );
+        pressure_labels = if series.len() > 1 {
+            series
+                .iter()
+                .map(|(tank, points)| {
+                    let first = points.first().map_or(0.0, |p| p.1);
+                    let last = points.last().map_or(0.0, |p| p.1);
+                    format!("{} ({:.0}-{:.0}bar)", dive.tank_label(*tank), first, last)
+                })
+                .collect()
+        } else {
+            vec![format!("Press ({:.0}-{:.0}bar)", pmax, pmin)]
+        };
+
+        for (i, (data, label)) in pressure_data.iter().zip(&pressure_labels).enumerate() {
+            datasets.push(
+                Dataset::default()
+                    .name(label.as_str())
+                    .marker(Marker::Braille)
+                    .graph_type(if app.interpolate_pressure {
+                        GraphType::Line
+                    } else {
+                        GraphType::Scatter
+                    })
+                    .style(Style::default().fg(TANK_COLORS[i % TANK_COLORS.len()]))
+                    .data(data),
+            );
+        }
     }
 
     // Depth (drawn last = top layer)
@@ -1,5 +1,8 @@
-use chrono::NaiveDateTime;
-use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{FixedOffset, NaiveDateTime, Offset, TimeZone};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 /// Model IDs from libdivecomputer descriptor table.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -44,9 +47,79 @@ impl Model {
             _ => Model::Unknown,
         }
     }
+
+    /// Canonical model name as reported by the device.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Model::IconHD => "Icon HD",
+            Model::IconAir => "Icon AIR",
+            Model::PuckPro => "Puck Pro",
+            Model::NemoWide2 => "Nemo Wide 2",
+            Model::Genius => "Genius",
+            Model::Puck2 => "Puck 2",
+            Model::QuadAir => "Quad Air",
+            Model::SmartAir => "Smart Air",
+            Model::Quad => "Quad",
+            Model::Horizon => "Horizon",
+            Model::PuckAir2 => "Puck Air 2",
+            Model::Sirius => "Sirius",
+            Model::QuadCi => "Quad Ci",
+            Model::Quad2 => "Quad2",
+            Model::Puck4 => "Puck4",
+            Model::Unknown => "Unknown",
+        }
+    }
+
+    /// Whether the model stores dives in the GENIUS layout (200-byte header,
+    /// tagged DSTR/DPRS/AIRS profile records). Older models use the
+    /// iconhd-family layouts; unknown BLE models are assumed to be GENIUS.
+    pub fn is_genius(&self) -> bool {
+        matches!(
+            self,
+            Model::Genius
+                | Model::Horizon
+                | Model::Sirius
+                | Model::QuadCi
+                | Model::Quad2
+                | Model::Puck4
+                | Model::Unknown
+        )
+    }
 }
 
-/// Dive mode from the GENIUS settings field.
+impl FromStr for Model {
+    type Err = String;
+
+    /// Case-insensitive model name, e.g. "sirius" or "icon hd".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let model = Model::from_name(s);
+        if model != Model::Unknown {
+            return Ok(model);
+        }
+        const ALL: [Model; 15] = [
+            Model::IconHD,
+            Model::IconAir,
+            Model::PuckPro,
+            Model::NemoWide2,
+            Model::Genius,
+            Model::Puck2,
+            Model::QuadAir,
+            Model::SmartAir,
+            Model::Quad,
+            Model::Horizon,
+            Model::PuckAir2,
+            Model::Sirius,
+            Model::QuadCi,
+            Model::Quad2,
+            Model::Puck4,
+        ];
+        ALL.into_iter()
+            .find(|m| m.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| format!("unknown model {s:?}"))
+    }
+}
+
+/// Dive mode from the header settings field.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum DiveMode {
@@ -56,12 +129,138 @@ pub enum DiveMode {
     Freedive,
 }
 
+/// Time zone of the dive computer clock: a fixed UTC offset ("+02:00") or an
+/// IANA zone name ("Europe/Paris").
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiveTz {
+    Fixed(FixedOffset),
+    Iana(chrono_tz::Tz),
+}
+
+impl DiveTz {
+    /// UTC offset in effect at the given local time.
+    pub fn offset_at(&self, local: NaiveDateTime) -> Option<FixedOffset> {
+        match self {
+            DiveTz::Fixed(offset) => Some(*offset),
+            DiveTz::Iana(tz) => tz
+                .offset_from_local_datetime(&local)
+                .earliest()
+                .map(|o| o.fix()),
+        }
+    }
+}
+
+impl FromStr for DiveTz {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("utc") || s == "Z" {
+            return Ok(DiveTz::Fixed(FixedOffset::east_opt(0).unwrap()));
+        }
+        if let Ok(tz) = s.parse::<chrono_tz::Tz>() {
+            return Ok(DiveTz::Iana(tz));
+        }
+
+        // Fixed offset: "+02:00", "+0200", "-05:30", "+2"
+        let err = || format!("invalid time zone {s:?} (expected e.g. \"+02:00\" or \"Europe/Paris\")");
+        let (sign, rest) = match s.as_bytes().first() {
+            Some(b'+') => (1, &s[1..]),
+            Some(b'-') => (-1, &s[1..]),
+            _ => return Err(err()),
+        };
+        let (hours, minutes) = match rest.split_once(':') {
+            Some((h, m)) => (h, m),
+            None if rest.len() == 4 => rest.split_at(2),
+            None => (rest, "0"),
+        };
+        let hours: i32 = hours.parse().map_err(|_| err())?;
+        let minutes: i32 = minutes.parse().map_err(|_| err())?;
+        if hours > 14 || minutes > 59 {
+            return Err(err());
+        }
+        FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+            .map(DiveTz::Fixed)
+            .ok_or_else(err)
+    }
+}
+
+impl fmt::Display for DiveTz {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiveTz::Fixed(offset) => write!(f, "{offset}"),
+            DiveTz::Iana(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+impl Serialize for DiveTz {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for DiveTz {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 /// A single gas mix.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GasMix {
     pub o2: u8,
 }
 
+impl GasMix {
+    /// Common name of the mix: "Air" or "EAN32".
+    pub fn name(&self) -> String {
+        if self.o2 == 21 {
+            "Air".to_string()
+        } else {
+            format!("EAN{}", self.o2)
+        }
+    }
+}
+
+/// A tank slot from the dive header, read by a wireless transmitter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tank {
+    /// Tank / transmitter slot (0-4) in the dive header.
+    pub index: u8,
+    /// Index into `DiveLog::gas_mixes` of the gas in this tank.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub gas_mix: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub begin_pressure_bar: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub end_pressure_bar: Option<f64>,
+    /// Pressure readings from the transmitter, in the order they arrived.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub readings: Vec<PressureReading>,
+}
+
+/// A tank pressure reading (one AIRS record), stamped with the time of the
+/// sample it followed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PressureReading {
+    pub time_s: u32,
+    pub pressure_bar: f64,
+}
+
+/// How tank pressure readings are mapped onto sample rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum PressureFill {
+    /// Only on the rows a reading was taken at
+    None,
+    /// Carry the last reading forward
+    Hold,
+    /// Interpolate linearly between readings
+    #[default]
+    Linear,
+}
+
 /// A single dive sample point.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Sample {
@@ -69,27 +268,471 @@ pub struct Sample {
     pub depth_m: f64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temp_c: Option<f64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Carried-forward tank pressure from files saved before per-tank
+    /// readings. New dives keep pressures in `DiveLog::tanks` instead.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
     pub pressure_bar: Option<f64>,
 }
 
+/// A single breath-hold within a freedive session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Apnea {
+    /// 1-based position in the session.
+    pub number: u32,
+    pub start_s: u32,
+    pub duration_s: u32,
+    pub max_depth_m: f64,
+    /// Average descent speed down to the deepest point (m/s).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub descent_speed: Option<f64>,
+    /// Average ascent speed from the deepest point (m/s).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub ascent_speed: Option<f64>,
+    /// Surface recovery time before the next apnea (none after the last one).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub surface_s: Option<u32>,
+}
+
+/// The dive computer a dive was downloaded from.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct DiveComputer {
+    pub model: String,
+    /// PCB number reported by the device (object 0x2000 sub 4).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub serial: Option<String>,
+}
+
+/// Logbook details the dive computer does not record, e.g. from an SSI
+/// export.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiveDetails {
+    /// Kind of dive as logged, e.g. "Fun dive" or "Training".
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub dive_type: Option<String>,
+    /// Body of water, e.g. "Ocean" or "Lake".
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub water_body: Option<String>,
+    /// Water type, e.g. "Salt" or "Fresh".
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub water_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub visibility_m: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub weather: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub air_temp_c: Option<f64>,
+    /// Entry, e.g. "Boat" or "Shore".
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub entry: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub weight_kg: Option<f64>,
+    /// Rating from 1 to 5.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub rating: Option<u8>,
+}
+
+impl DiveDetails {
+    pub fn is_empty(&self) -> bool {
+        *self == DiveDetails::default()
+    }
+
+    /// Label and display value of every field that is set.
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let water: Vec<&str> = [self.water_body.as_deref(), self.water_type.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect();
+        [
+            ("Dive type", self.dive_type.clone()),
+            ("Water", (!water.is_empty()).then(|| water.join(", "))),
+            ("Visibility", self.visibility_m.map(|v| format!("{:.0} m", v))),
+            ("Weather", self.weather.clone()),
+            ("Air temp", self.air_temp_c.map(|t| format!("{:.1} °C", t))),
+            ("Entry", self.entry.clone()),
+            ("Weight", self.weight_kg.map(|w| format!("{:.1} kg", w))),
+            ("Rating", self.rating.map(|r| format!("{}/5", r))),
+        ]
+        .into_iter()
+        .filter_map(|(label, value)| Some((label, value?)))
+        .collect()
+    }
+}
+
 /// A parsed dive log entry.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DiveLog {
     pub number: u32,
     #[serde(with = "datetime_format")]
     pub datetime: NaiveDateTime,
+    /// Start time with seconds, recovered from the profile records.
+    /// The header `datetime` only has minute resolution.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        default,
+        with = "datetime_format::option"
+    )]
+    pub start_time: Option<NaiveDateTime>,
+    /// Zone of the dive computer clock that `datetime` and `start_time` are
+    /// expressed in. Unknown for dives downloaded without `--tz`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub timezone: Option<DiveTz>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub computer: Option<DiveComputer>,
     pub duration_seconds: u32,
     pub max_depth_m: f64,
+    /// Minimum water temperature from the dive header.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub min_temp_c: Option<f64>,
+    /// Maximum water temperature from the dive header.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_temp_c: Option<f64>,
     pub dive_mode: DiveMode,
     pub gas_mixes: Vec<GasMix>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub tanks: Vec<Tank>,
     pub samples: Vec<Sample>,
+    /// Individual apneas of a freedive session.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub apneas: Vec<Apnea>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub site: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub country: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub buddy: Option<String>,
+    /// Free-text notes, e.g. from an imported logbook.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub notes: Option<String>,
+    #[serde(skip_serializing_if = "DiveDetails::is_empty", default)]
+    pub details: DiveDetails,
+    /// Logbook file the dive was imported from; `None` for dives downloaded
+    /// from the device.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub source: Option<String>,
+}
+
+/// Resolution of a dive start time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimePrecision {
+    Minute,
+    Second,
+}
+
+impl DiveLog {
+    /// Drop everything that identifies the diver: site, country, buddies,
+    /// notes and the dive computer serial.
+    pub fn anonymize(&mut self) {
+        self.site = None;
+        self.country = None;
+        self.buddy = None;
+        self.notes = None;
+        if let Some(dc) = &mut self.computer {
+            dc.serial = None;
+        }
+    }
+
+    /// Best known start time: the seconds-resolution `start_time` when the
+    /// parser recovered one, otherwise the minute-resolution header `datetime`.
+    pub fn start(&self) -> (NaiveDateTime, TimePrecision) {
+        match self.start_time {
+            Some(t) => (t, TimePrecision::Second),
+            None => (self.datetime, TimePrecision::Minute),
+        }
+    }
+
+    /// Water temperature range: from the samples when they carry temperature,
+    /// otherwise from the header.
+    pub fn temp_range(&self) -> Option<(f64, f64)> {
+        let from_samples = self
+            .samples
+            .iter()
+            .filter_map(|s| s.temp_c)
+            .fold(None, |acc: Option<(f64, f64)>, t| match acc {
+                Some((min, max)) => Some((min.min(t), max.max(t))),
+                None => Some((t, t)),
+            });
+        from_samples.or(match (self.min_temp_c, self.max_temp_c) {
+            (Some(min), Some(max)) => Some((min, max)),
+            (Some(t), None) | (None, Some(t)) => Some((t, t)),
+            (None, None) => None,
+        })
+    }
+
+    /// Pressure readings per tank as `(tank index, readings)`. Dives saved
+    /// before per-tank readings fall back to the carried-forward
+    /// `Sample::pressure_bar` as tank 0, keeping only the steps.
+    pub fn pressure_series(&self) -> Vec<(u8, Vec<PressureReading>)> {
+        let series: Vec<_> = self
+            .tanks
+            .iter()
+            .filter(|t| !t.readings.is_empty())
+            .map(|t| (t.index, t.readings.clone()))
+            .collect();
+        if !series.is_empty() {
+            return series;
+        }
+
+        let mut legacy = Vec::new();
+        let mut last = None;
+        for s in &self.samples {
+            if let Some(p) = s.pressure_bar {
+                if last != Some(p) {
+                    legacy.push(PressureReading {
+                        time_s: s.time_s,
+                        pressure_bar: p,
+                    });
+                    last = Some(p);
+                }
+            }
+        }
+        if legacy.is_empty() {
+            Vec::new()
+        } else {
+            vec![(0, legacy)]
+        }
+    }
+
+    /// Points a tank's pressure line passes through: its readings, anchored
+    /// at the dive start and end by the header begin / end pressures.
+    pub fn pressure_points(&self, tank: u8, readings: &[PressureReading]) -> Vec<(u32, f64)> {
+        let header = self.tanks.iter().find(|t| t.index == tank);
+        let last_time = self.samples.last().map_or(0, |s| s.time_s);
+
+        let mut points = Vec::with_capacity(readings.len() + 2);
+        if let Some(begin) = header.and_then(|t| t.begin_pressure_bar) {
+            if readings.first().is_some_and(|r| r.time_s > 0) {
+                points.push((0, begin));
+            }
+        }
+        points.extend(readings.iter().map(|r| (r.time_s, r.pressure_bar)));
+        if let Some(end) = header.and_then(|t| t.end_pressure_bar) {
+            if readings.last().is_some_and(|r| r.time_s < last_time) {
+                points.push((last_time, end));
+            }
+        }
+        points
+    }
+
+    /// Start and end pressure of each tank with readings, as `(tank index,
+    /// start, end)`.
+    pub fn tank_pressures(&self) -> Vec<(u8, f64, f64)> {
+        self.pressure_series()
+            .into_iter()
+            .filter_map(|(tank, readings)| {
+                let points = self.pressure_points(tank, &readings);
+                Some((tank, points.first()?.1, points.last()?.1))
+            })
+            .collect()
+    }
+
+    /// Time-weighted average depth over the samples.
+    pub fn avg_depth(&self) -> Option<f64> {
+        let (first, last) = (self.samples.first()?, self.samples.last()?);
+        let span = last.time_s.saturating_sub(first.time_s);
+        if span == 0 {
+            return None;
+        }
+        let area: f64 = self
+            .samples
+            .windows(2)
+            .map(|w| (w[0].depth_m + w[1].depth_m) / 2.0 * w[1].time_s.saturating_sub(w[0].time_s) as f64)
+            .sum();
+        Some(area / span as f64)
+    }
+
+    /// Pressure of each tank at every sample, as `(tank index, one value per
+    /// sample)`, for exports that need one value per row.
+    pub fn sample_pressures(&self, fill: PressureFill) -> Vec<(u8, Vec<Option<f64>>)> {
+        self.pressure_series()
+            .into_iter()
+            .map(|(tank, readings)| {
+                let values = match fill {
+                    PressureFill::None => self
+                        .samples
+                        .iter()
+                        .map(|s| {
+                            readings
+                                .iter()
+                                .rev()
+                                .find(|r| r.time_s == s.time_s)
+                                .map(|r| r.pressure_bar)
+                        })
+                        .collect(),
+                    PressureFill::Hold => {
+                        let points = self.pressure_points(tank, &readings);
+                        self.samples
+                            .iter()
+                            .map(|s| {
+                                points
+                                    .iter()
+                                    .take_while(|(t, _)| *t <= s.time_s)
+                                    .last()
+                                    .map(|(_, p)| *p)
+                            })
+                            .collect()
+                    }
+                    PressureFill::Linear => {
+                        let points = self.pressure_points(tank, &readings);
+                        self.samples
+                            .iter()
+                            .map(|s| interpolate(&points, s.time_s))
+                            .collect()
+                    }
+                };
+                (tank, values)
+            })
+            .collect()
+    }
+
+    /// Display label for a tank: "T1", "T2"... with its gas when known.
+    pub fn tank_label(&self, index: u8) -> String {
+        let gas = self
+            .tanks
+            .iter()
+            .find(|t| t.index == index)
+            .and_then(|t| t.gas_mix)
+            .and_then(|g| self.gas_mixes.get(g));
+        match gas {
+            Some(g) => format!("T{} {}%", index + 1, g.o2),
+            None => format!("T{}", index + 1),
+        }
+    }
+
+    /// Individual buddies from the correlated `buddy` field, which may list
+    /// several names separated by commas or semicolons.
+    pub fn buddies(&self) -> Vec<&str> {
+        self.buddy
+            .as_deref()
+            .map(|b| {
+                b.split([',', ';'])
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Start time on the dive computer clock in ISO 8601, with the UTC offset
+    /// when the zone is known.
+    pub fn start_iso(&self) -> String {
+        let (start, _) = self.start();
+        let local = start.format("%Y-%m-%dT%H:%M:%S").to_string();
+        match self.utc_offset() {
+            Some(offset) => format!("{}{}", local, offset),
+            None => local,
+        }
+    }
+
+    /// UTC offset of the dive computer clock at the start of the dive.
+    pub fn utc_offset(&self) -> Option<FixedOffset> {
+        self.timezone.and_then(|tz| tz.offset_at(self.datetime))
+    }
+
+    /// Best known start time in UTC. Without a known zone the dive computer
+    /// clock is assumed to run on UTC.
+    pub fn start_utc(&self) -> NaiveDateTime {
+        let (start, _) = self.start();
+        match self.utc_offset() {
+            Some(offset) => start - chrono::Duration::seconds(offset.local_minus_utc() as i64),
+            None => start,
+        }
+    }
+
+    /// Whether two dives overlap in time, e.g. the same dive logged by two
+    /// computers or imported twice. Compared in UTC when both zones are known,
+    /// otherwise on the local clock.
+    pub fn overlaps(&self, other: &DiveLog) -> bool {
+        let both_zoned = self.utc_offset().is_some() && other.utc_offset().is_some();
+        let span = |d: &DiveLog| {
+            let start = if both_zoned { d.start_utc() } else { d.start().0 };
+            (start, start + chrono::Duration::seconds(d.duration_seconds as i64))
+        };
+        let (a_start, a_end) = span(self);
+        let (b_start, b_end) = span(other);
+        a_start <= b_end && b_start <= a_end
+    }
+
+    /// Convert a UTC time to the dive's local clock (identity if unknown).
+    pub fn to_local(&self, utc: NaiveDateTime) -> NaiveDateTime {
+        match self.utc_offset() {
+            Some(offset) => utc + chrono::Duration::seconds(offset.local_minus_utc() as i64),
+            None => utc,
+        }
+    }
+}
+
+#[cfg(test)]
+impl DiveLog {
+    /// Test fixture: a short nitrox dive on 2025-03-04 10:15 with one tank,
+    /// temperature on every sample and no site or buddy. Tests override the
+    /// fields they exercise with struct update syntax.
+    pub fn sample() -> DiveLog {
+        let sample = |time_s, depth_m, temp_c| Sample {
+            time_s,
+            depth_m,
+            temp_c: Some(temp_c),
+            pressure_bar: None,
+        };
+        DiveLog {
+            number: 12,
+            datetime: NaiveDateTime::parse_from_str("2025-03-04 10:15:00", "%Y-%m-%d %H:%M:%S")
+                .unwrap(),
+            start_time: None,
+            timezone: None,
+            computer: Some(DiveComputer {
+                model: "Sirius".to_string(),
+                serial: Some("123456".to_string()),
+            }),
+            duration_seconds: 20,
+            max_depth_m: 12.3,
+            min_temp_c: None,
+            max_temp_c: None,
+            dive_mode: DiveMode::Nitrox,
+            gas_mixes: vec![GasMix { o2: 32 }],
+            tanks: vec![Tank {
+                index: 0,
+                gas_mix: Some(0),
+                begin_pressure_bar: None,
+                end_pressure_bar: None,
+                readings: vec![
+                    PressureReading { time_s: 0, pressure_bar: 200.0 },
+                    PressureReading { time_s: 10, pressure_bar: 190.0 },
+                ],
+            }],
+            samples: vec![sample(0, 0.0, 27.0), sample(10, 12.3, 26.5), sample(20, 0.0, 26.5)],
+            apneas: Vec::new(),
+            site: None,
+            country: None,
+            buddy: None,
+            notes: None,
+            details: DiveDetails::default(),
+            source: None,
+        }
+    }
+
+    /// `sample()` starting at `datetime` ("YYYY-MM-DD HH:MM").
+    pub fn sample_at(number: u32, datetime: &str) -> DiveLog {
+        DiveLog {
+            number,
+            datetime: NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M").unwrap(),
+            ..DiveLog::sample()
+        }
+    }
+}
+
+/// Linear interpolation through time-ordered points; `None` outside them.
+fn interpolate(points: &[(u32, f64)], time_s: u32) -> Option<f64> {
+    let i = points.partition_point(|(t, _)| *t <= time_s);
+    if i == 0 {
+        return None;
+    }
+    let (t0, p0) = points[i - 1];
+    if t0 == time_s {
+        return Some(p0);
+    }
+    let (t1, p1) = *points.get(i)?;
+    let frac = (time_s - t0) as f64 / (t1 - t0) as f64;
+    Some(p0 + (p1 - p0) * frac)
 }
 
 /// Collection of all parsed dives.
@@ -126,4 +769,29 @@ mod datetime_format {
         let s: String = serde::Deserialize::deserialize(deserializer)?;
         NaiveDateTime::parse_from_str(&s, FORMAT).map_err(serde::de::Error::custom)
     }
+
+    pub mod option {
+        use chrono::NaiveDateTime;
+        use serde::{self, Deserializer, Serializer};
+
+        pub fn serialize<S>(date: &Option<NaiveDateTime>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match date {
+                Some(d) => super::serialize(d, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<NaiveDateTime>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let s: Option<String> = serde::Deserialize::deserialize(deserializer)?;
+            s.map(|s| NaiveDateTime::parse_from_str(&s, super::FORMAT))
+                .transpose()
+                .map_err(serde::de::Error::custom)
+        }
+    }
 }
@@ -0,0 +1,644 @@
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDateTime, Timelike};
+
+use crate::freedive;
+use crate::types::*;
+use crate::xml::{child, child_f64, child_text, path, XmlWriter};
+
+const UDDF_NAMESPACE: &str = "http://www.streit.cc/uddf/3.2/";
+
+// UDDF uses SI units: depth in metres, temperatures in Kelvin, pressures in
+// Pascal and times in seconds.
+
+fn kelvin(celsius: f64) -> String {
+    format!("{:.2}", celsius + 273.15)
+}
+
+fn pascal(bar: f64) -> String {
+    format!("{:.0}", bar * 100_000.0)
+}
+
+fn mix_id(o2: u8) -> String {
+    format!("mix_{}", o2)
+}
+
+fn computer_id(index: usize) -> String {
+    format!("dc_{}", index + 1)
+}
+
+// Dive and tank ids are built from the position of the dive in the document:
+// dive numbers are not unique once imported dives or several computers are
+// mixed in one logbook.
+fn dive_id(index: usize) -> String {
+    format!("dive_{}", index + 1)
+}
+
+fn tank_id(dive: usize, tank: u8) -> String {
+    format!("{}_tank_{}", dive_id(dive), tank + 1)
+}
+
+/// Split a buddy name into first name and last name (last word).
+fn split_name(name: &str) -> (&str, Option<&str>) {
+    match name.rsplit_once(' ') {
+        Some((first, last)) => (first.trim(), Some(last)),
+        None => (name, None),
+    }
+}
+
+/// Build a UDDF 3.2 document (https://www.streit.cc/uddf/) containing all
+/// `dives`, with the computers, buddies, sites and gas mixes they reference.
+pub fn dives_to_uddf(dives: &[DiveLog]) -> String {
+    // Shared definitions, referenced from the dives by id
+    let computers: Vec<_> = dives
+        .iter()
+        .filter_map(|d| d.computer.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let buddies: BTreeSet<&str> = dives.iter().flat_map(|d| d.buddies()).collect();
+    let buddies: Vec<&str> = buddies.into_iter().collect();
+    let sites: BTreeSet<(&str, Option<&str>)> = dives
+        .iter()
+        .filter_map(|d| Some((d.site.as_deref()?, d.country.as_deref())))
+        .collect();
+    let sites: Vec<(&str, Option<&str>)> = sites.into_iter().collect();
+    let mixes: BTreeSet<u8> = dives
+        .iter()
+        .flat_map(|d| d.gas_mixes.iter().map(|g| g.o2))
+        .collect();
+
+    let mut xml = XmlWriter::new();
+    xml.open("uddf", &[("xmlns", UDDF_NAMESPACE), ("version", "3.2.0")]);
+
+    xml.open("generator", &[]);
+    xml.leaf("name", &[], env!("CARGO_PKG_NAME"));
+    xml.leaf("type", &[], "converter");
+    xml.leaf("version", &[], env!("CARGO_PKG_VERSION"));
+    xml.close();
+
+    xml.open("diver", &[]);
+    xml.open("owner", &[("id", "owner")]);
+    if !computers.is_empty() {
+        xml.open("equipment", &[]);
+        for (i, dc) in computers.iter().enumerate() {
+            xml.open("divecomputer", &[("id", &computer_id(i))]);
+            xml.leaf("name", &[], &dc.model);
+            xml.leaf("model", &[], &dc.model);
+            if let Some(ref serial) = dc.serial {
+                xml.leaf("serialnumber", &[], serial);
+            }
+            xml.close();
+        }
+        xml.close();
+    }
+    xml.close();
+    for (i, name) in buddies.iter().enumerate() {
+        let (first, last) = split_name(name);
+        xml.open("buddy", &[("id", &format!("buddy_{}", i + 1))]);
+        xml.open("personal", &[]);
+        xml.leaf("firstname", &[], first);
+        if let Some(last) = last {
+            xml.leaf("lastname", &[], last);
+        }
+        xml.close();
+        xml.close();
+    }
+    xml.close();
+
+    if !sites.is_empty() {
+        xml.open("divesite", &[]);
+        for (i, (site, country)) in sites.iter().enumerate() {
+            xml.open("site", &[("id", &format!("site_{}", i + 1))]);
+            xml.leaf("name", &[], site);
+            if let Some(country) = country {
+                xml.open("geography", &[]);
+                xml.open("address", &[]);
+                xml.leaf("country", &[], country);
+                xml.close();
+                xml.close();
+            }
+            xml.close();
+        }
+        xml.close();
+    }
+
+    if !mixes.is_empty() {
+        xml.open("gasdefinitions", &[]);
+        for &o2 in &mixes {
+            xml.open("mix", &[("id", &mix_id(o2))]);
+            xml.leaf("name", &[], &GasMix { o2 }.name());
+            xml.leaf("o2", &[], &format!("{:.2}", o2 as f64 / 100.0));
+            xml.leaf("n2", &[], &format!("{:.2}", (100 - o2) as f64 / 100.0));
+            xml.leaf("he", &[], "0.00");
+            xml.close();
+        }
+        xml.close();
+    }
+
+    xml.open("profiledata", &[]);
+    xml.open("repetitiongroup", &[("id", "rg_1")]);
+    for (index, dive) in dives.iter().enumerate() {
+        let computer = dive
+            .computer
+            .as_ref()
+            .and_then(|dc| computers.iter().position(|c| c == dc));
+        let site = dive.site.as_deref().and_then(|name| {
+            sites
+                .iter()
+                .position(|&(s, c)| s == name && c == dive.country.as_deref())
+        });
+        write_dive(&mut xml, index, dive, computer, site, &buddies);
+    }
+    xml.close();
+    xml.close();
+
+    xml.close();
+    xml.finish()
+}
+
+fn write_dive(
+    xml: &mut XmlWriter,
+    position: usize,
+    dive: &DiveLog,
+    computer: Option<usize>,
+    site: Option<usize>,
+    buddies: &[&str],
+) {
+    xml.open("dive", &[("id", &dive_id(position))]);
+
+    xml.open("informationbeforedive", &[]);
+    if let Some(site) = site {
+        xml.empty("link", &[("ref", &format!("site_{}", site + 1))]);
+    }
+    for name in dive.buddies() {
+        if let Some(i) = buddies.iter().position(|b| *b == name) {
+            xml.empty("link", &[("ref", &format!("buddy_{}", i + 1))]);
+        }
+    }
+    xml.leaf("divenumber", &[], &dive.number.to_string());
+    xml.leaf("datetime", &[], &dive.start_iso());
+    if let Some(air) = dive.details.air_temp_c {
+        xml.leaf("airtemperature", &[], &kelvin(air));
+    }
+    if computer.is_some() || dive.details.weight_kg.is_some() {
+        xml.open("equipmentused", &[]);
+        if let Some(weight) = dive.details.weight_kg {
+            xml.leaf("leadquantity", &[], &format!("{:.1}", weight));
+        }
+        if let Some(computer) = computer {
+            xml.empty("link", &[("ref", &computer_id(computer))]);
+        }
+        xml.close();
+    }
+    xml.close();
+
+    // Tanks: header tank slots plus any pressure series without one (legacy)
+    let pressures = dive.sample_pressures(PressureFill::None);
+    let mut tanks: BTreeSet<u8> = dive.tanks.iter().map(|t| t.index).collect();
+    tanks.extend(pressures.iter().map(|(tank, _)| *tank));
+    for &index in &tanks {
+        let tank = dive.tanks.iter().find(|t| t.index == index);
+        let gas = tank
+            .and_then(|t| t.gas_mix)
+            .and_then(|g| dive.gas_mixes.get(g));
+        let points = dive
+            .pressure_series()
+            .into_iter()
+            .find(|(t, _)| *t == index)
+            .map(|(_, readings)| dive.pressure_points(index, &readings))
+            .unwrap_or_default();
+        let begin = tank
+            .and_then(|t| t.begin_pressure_bar)
+            .or(points.first().map(|p| p.1));
+        let end = tank
+            .and_then(|t| t.end_pressure_bar)
+            .or(points.last().map(|p| p.1));
+
+        xml.open("tankdata", &[("id", &tank_id(position, index))]);
+        if let Some(gas) = gas {
+            xml.empty("link", &[("ref", &mix_id(gas.o2))]);
+        }
+        if let Some(begin) = begin {
+            xml.leaf("tankpressurebegin", &[], &pascal(begin));
+        }
+        if let Some(end) = end {
+            xml.leaf("tankpressureend", &[], &pascal(end));
+        }
+        xml.close();
+    }
+
+    xml.open("samples", &[]);
+    for (i, sample) in dive.samples.iter().enumerate() {
+        xml.open("waypoint", &[]);
+        xml.leaf("depth", &[], &format!("{:.2}", sample.depth_m));
+        if i == 0 {
+            let mode = if dive.dive_mode == DiveMode::Freedive { "apnoe" } else { "opencircuit" };
+            xml.empty("divemode", &[("type", mode)]);
+        }
+        xml.leaf("divetime", &[], &sample.time_s.to_string());
+        if i == 0 {
+            if let Some(gas) = dive.gas_mixes.first() {
+                xml.empty("switchmix", &[("ref", &mix_id(gas.o2))]);
+            }
+        }
+        for (tank, values) in &pressures {
+            if let Some(bar) = values[i] {
+                xml.leaf("tankpressure", &[("ref", &tank_id(position, *tank))], &pascal(bar));
+            }
+        }
+        if let Some(temp) = sample.temp_c {
+            xml.leaf("temperature", &[], &kelvin(temp));
+        }
+        xml.close();
+    }
+    xml.close();
+
+    xml.open("informationafterdive", &[]);
+    xml.leaf("diveduration", &[], &dive.duration_seconds.to_string());
+    xml.leaf("greatestdepth", &[], &format!("{:.2}", dive.max_depth_m));
+    if let Some((min, _)) = dive.temp_range() {
+        xml.leaf("lowesttemperature", &[], &kelvin(min));
+    }
+    if let Some(ref notes) = dive.notes {
+        xml.open("notes", &[]);
+        for para in notes.lines() {
+            xml.leaf("para", &[], para);
+        }
+        xml.close();
+    }
+    if let Some(rating) = dive.details.rating {
+        // UDDF rates from 1 to 10
+        xml.open("rating", &[]);
+        xml.leaf("ratingvalue", &[], &(rating * 2).to_string());
+        xml.close();
+    }
+    if let Some(visibility) = dive.details.visibility_m {
+        xml.leaf("visibility", &[], &format!("{:.1}", visibility));
+    }
+    xml.close();
+
+    xml.close();
+}
+
+/// Read the dives of a UDDF document (3.x) into `DiveLog`s.
+pub fn parse_uddf(text: &str) -> Result<Vec<DiveLog>> {
+    let doc = roxmltree::Document::parse(text).context("Invalid XML")?;
+    let root = doc.root_element();
+    if !root.has_tag_name("uddf") {
+        bail!("Not a UDDF document (root element <{}>)", root.tag_name().name());
+    }
+
+    // Definitions referenced from the dives by id
+    let with_id = |name: &'static str| {
+        root.descendants()
+            .filter(move |n| n.has_tag_name(name))
+            .filter_map(|n| Some((n.attribute("id")?, n)))
+    };
+    let mixes: HashMap<&str, u8> = with_id("mix")
+        .map(|(id, mix)| {
+            let o2 = child_f64(mix, "o2").map_or(21, |f| (f * 100.0).round() as u8);
+            (id, o2)
+        })
+        .collect();
+    let buddies: HashMap<&str, String> = with_id("buddy")
+        .filter_map(|(id, buddy)| {
+            let personal = child(buddy, "personal")?;
+            let name = ["firstname", "middlename", "lastname"]
+                .iter()
+                .filter_map(|part| child_text(personal, part))
+                .collect::<Vec<_>>()
+                .join(" ");
+            (!name.is_empty()).then_some((id, name))
+        })
+        .collect();
+    let sites: HashMap<&str, (Option<&str>, Option<&str>)> = with_id("site")
+        .map(|(id, site)| {
+            let country = path(site, &["geography", "address"])
+                .and_then(|address| child_text(address, "country"));
+            (id, (child_text(site, "name"), country))
+        })
+        .collect();
+    let computers: HashMap<&str, DiveComputer> = with_id("divecomputer")
+        .filter_map(|(id, dc)| {
+            let model = child_text(dc, "model").or(child_text(dc, "name"))?;
+            let computer = DiveComputer {
+                model: model.to_string(),
+                serial: child_text(dc, "serialnumber").map(str::to_string),
+            };
+            Some((id, computer))
+        })
+        .collect();
+
+    let mut dives = Vec::new();
+    for (i, node) in root
+        .descendants()
+        .filter(|n| n.has_tag_name("dive"))
+        .enumerate()
+    {
+        let before = child(node, "informationbeforedive");
+        let after = child(node, "informationafterdive");
+
+        let Some((start, timezone)) = before
+            .and_then(|b| child_text(b, "datetime"))
+            .and_then(parse_datetime)
+        else {
+            eprintln!(
+                "Warning: skipping UDDF dive {} without a valid date",
+                node.attribute("id").unwrap_or("?")
+            );
+            continue;
+        };
+
+        let mut site = None;
+        let mut buddy_names = Vec::new();
+        let mut computer = None;
+        let links = before
+            .into_iter()
+            .flat_map(|b| {
+                b.children()
+                    .chain(child(b, "equipmentused").into_iter().flat_map(|e| e.children()))
+            })
+            .filter(|n| n.has_tag_name("link"))
+            .filter_map(|n| n.attribute("ref"));
+        for id in links {
+            if let Some(s) = sites.get(id) {
+                site = Some(*s);
+            } else if let Some(name) = buddies.get(id) {
+                buddy_names.push(name.as_str());
+            } else if let Some(dc) = computers.get(id) {
+                computer = Some(dc.clone());
+            }
+        }
+
+        // Gas mixes in order of use, with tanks referring to them by index
+        let mut gas_mixes: Vec<GasMix> = Vec::new();
+        let mut gas_index = |o2: u8| match gas_mixes.iter().position(|g| g.o2 == o2) {
+            Some(i) => i,
+            None => {
+                gas_mixes.push(GasMix { o2 });
+                gas_mixes.len() - 1
+            }
+        };
+
+        let tank_nodes: Vec<_> = node.children().filter(|n| n.has_tag_name("tankdata")).collect();
+        let mut tanks: Vec<Tank> = tank_nodes
+            .iter()
+            .enumerate()
+            .map(|(index, tank)| {
+                let o2 = child(*tank, "link")
+                    .and_then(|l| l.attribute("ref"))
+                    .and_then(|id| mixes.get(id));
+                Tank {
+                    index: index as u8,
+                    gas_mix: o2.map(|&o2| gas_index(o2)),
+                    begin_pressure_bar: child_f64(*tank, "tankpressurebegin").map(|pa| pa / 100_000.0),
+                    end_pressure_bar: child_f64(*tank, "tankpressureend").map(|pa| pa / 100_000.0),
+                    readings: Vec::new(),
+                }
+            })
+            .collect();
+
+        let mut samples = Vec::new();
+        let mut freedive = false;
+        let waypoints = child(node, "samples")
+            .into_iter()
+            .flat_map(|s| s.children())
+            .filter(|n| n.has_tag_name("waypoint"));
+        for waypoint in waypoints {
+            let (Some(time), Some(depth_m)) =
+                (child_f64(waypoint, "divetime"), child_f64(waypoint, "depth"))
+            else {
+                continue;
+            };
+            let time_s = time.round() as u32;
+            if let Some(mode) = child(waypoint, "divemode").and_then(|m| m.attribute("type")) {
+                freedive = mode == "apnoe";
+            }
+            if let Some(&o2) = child(waypoint, "switchmix")
+                .and_then(|m| m.attribute("ref"))
+                .and_then(|id| mixes.get(id))
+            {
+                gas_index(o2);
+            }
+            for pressure in waypoint.children().filter(|n| n.has_tag_name("tankpressure")) {
+                let Some(pa) = pressure.text().and_then(|t| t.trim().parse::<f64>().ok()) else {
+                    continue;
+                };
+                // Without a reference the pressure belongs to the only tank
+                let index = pressure
+                    .attribute("ref")
+                    .and_then(|id| tank_nodes.iter().position(|t| t.attribute("id") == Some(id)))
+                    .unwrap_or(0);
+                if tanks.is_empty() {
+                    tanks.push(Tank {
+                        index: 0,
+                        gas_mix: None,
+                        begin_pressure_bar: None,
+                        end_pressure_bar: None,
+                        readings: Vec::new(),
+                    });
+                }
+                tanks[index].readings.push(PressureReading {
+                    time_s,
+                    pressure_bar: pa / 100_000.0,
+                });
+            }
+            samples.push(Sample {
+                time_s,
+                depth_m,
+                temp_c: child_f64(waypoint, "temperature").map(|k| k - 273.15),
+                pressure_bar: None,
+            });
+        }
+        tanks.retain(|t| {
+            t.begin_pressure_bar.is_some() || t.end_pressure_bar.is_some() || !t.readings.is_empty()
+        });
+
+        let dive_mode = if freedive {
+            DiveMode::Freedive
+        } else if gas_mixes.iter().any(|g| g.o2 > 21) {
+            DiveMode::Nitrox
+        } else {
+            DiveMode::Air
+        };
+        if gas_mixes.is_empty() {
+            gas_mixes.push(GasMix { o2: 21 });
+        }
+
+        let last_sample_s = samples.last().map_or(0, |s| s.time_s);
+        let sample_max_depth = samples.iter().map(|s| s.depth_m).fold(0.0, f64::max);
+        let lowest_temp = after
+            .and_then(|a| child_f64(a, "lowesttemperature"))
+            .map(|k| k - 273.15);
+
+        let (site, country) = site.unwrap_or_default();
+        let mut dive = DiveLog {
+            number: before
+                .and_then(|b| child_text(b, "divenumber"))
+                .and_then(|n| n.parse().ok())
+                .unwrap_or(i as u32 + 1),
+            datetime: start.with_second(0).unwrap_or(start),
+            start_time: (start.second() != 0).then_some(start),
+            timezone,
+            computer,
+            duration_seconds: after
+                .and_then(|a| child_f64(a, "diveduration"))
+                .map_or(last_sample_s, |d| d.round() as u32),
+            max_depth_m: after
+                .and_then(|a| child_f64(a, "greatestdepth"))
+                .unwrap_or(sample_max_depth),
+            min_temp_c: lowest_temp,
+            max_temp_c: None,
+            dive_mode,
+            gas_mixes,
+            tanks,
+            samples,
+            apneas: Vec::new(),
+            site: site.map(str::to_string),
+            country: country.map(str::to_string),
+            buddy: (!buddy_names.is_empty()).then(|| buddy_names.join(", ")),
+            notes: after.and_then(|a| child(a, "notes")).and_then(notes_text),
+            details: DiveDetails {
+                air_temp_c: before
+                    .and_then(|b| child_f64(b, "airtemperature"))
+                    .map(|k| k - 273.15),
+                weight_kg: before
+                    .and_then(|b| path(b, &["equipmentused", "leadquantity"]))
+                    .and_then(|n| n.text()?.trim().parse().ok()),
+                rating: after
+                    .and_then(|a| path(a, &["rating", "ratingvalue"]))
+                    .and_then(|n| n.text()?.trim().parse::<u8>().ok())
+                    .map(|r| r.div_ceil(2).clamp(1, 5)),
+                visibility_m: after.and_then(|a| child_f64(a, "visibility")),
+                ..DiveDetails::default()
+            },
+            source: None,
+        };
+        if dive.dive_mode == DiveMode::Freedive {
+            dive.apneas = freedive::split_apneas(&dive.samples);
+        }
+        dives.push(dive);
+    }
+
+    Ok(dives)
+}
+
+/// Paragraphs of a UDDF `<notes>` element, one per line.
+fn notes_text(notes: roxmltree::Node) -> Option<String> {
+    let paras: Vec<&str> = notes
+        .children()
+        .filter(|n| n.has_tag_name("para"))
+        .filter_map(|n| n.text())
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .collect();
+    (!paras.is_empty()).then(|| paras.join("\n"))
+}
+
+/// UDDF date/time: ISO 8601 with or without UTC offset and seconds.
+fn parse_datetime(text: &str) -> Option<(NaiveDateTime, Option<DiveTz>)> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(text) {
+        return Some((dt.naive_local(), Some(DiveTz::Fixed(*dt.offset()))));
+    }
+    ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(text, fmt).ok())
+        .map(|dt| (dt, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A dive with two tanks (air and nitrox), each with pressure readings.
+    fn twin_tank_dive(number: u32, datetime: &str, serial: &str) -> DiveLog {
+        let tank = |index: u8, readings: [(u32, f64); 2]| Tank {
+            index,
+            gas_mix: Some(index as usize),
+            begin_pressure_bar: None,
+            end_pressure_bar: None,
+            readings: readings
+                .map(|(time_s, pressure_bar)| PressureReading { time_s, pressure_bar })
+                .to_vec(),
+        };
+        DiveLog {
+            computer: Some(DiveComputer {
+                model: "Sirius".to_string(),
+                serial: Some(serial.to_string()),
+            }),
+            gas_mixes: vec![GasMix { o2: 21 }, GasMix { o2: 32 }],
+            tanks: vec![tank(0, [(0, 200.0), (20, 180.0)]), tank(1, [(10, 210.0), (20, 205.0)])],
+            site: Some("Blue Corner".to_string()),
+            country: Some("Palau".to_string()),
+            buddy: Some("Jane Doe".to_string()),
+            ..DiveLog::sample_at(number, datetime)
+        }
+    }
+
+    /// Two dives numbered 12 from different computers, and a third dive.
+    fn logbook() -> Vec<DiveLog> {
+        vec![
+            twin_tank_dive(12, "2025-03-04 10:15", "A1"),
+            twin_tank_dive(12, "2025-03-05 09:00", "B2"),
+            twin_tank_dive(13, "2025-03-05 14:30", "A1"),
+        ]
+    }
+
+    #[test]
+    fn ids_are_unique_and_refs_resolve() {
+        let text = dives_to_uddf(&logbook());
+        let doc = roxmltree::Document::parse(&text).unwrap();
+
+        let mut ids = BTreeSet::new();
+        for id in doc.descendants().filter_map(|n| n.attribute("id")) {
+            assert!(ids.insert(id), "duplicate id {id}");
+        }
+        let refs: Vec<&str> = doc.descendants().filter_map(|n| n.attribute("ref")).collect();
+        assert!(refs.contains(&"dive_2_tank_2"));
+        for r in refs {
+            assert!(ids.contains(r), "dangling ref {r}");
+        }
+    }
+
+    #[test]
+    fn dives_with_the_same_number_round_trip() {
+        let dives = parse_uddf(&dives_to_uddf(&logbook())).unwrap();
+        assert_eq!(dives.len(), 3);
+        assert_eq!(
+            dives.iter().map(|d| d.number).collect::<Vec<_>>(),
+            [12, 12, 13]
+        );
+        for dive in &dives {
+            assert_eq!(dive.tanks.len(), 2);
+            assert_eq!(dive.tanks[1].readings.len(), 2);
+            assert_eq!(dive.tanks[1].readings[0].pressure_bar, 210.0);
+        }
+    }
+
+    /// Validate against the UDDF 3.2 schema with xmllint. The schema is not
+    /// bundled: point `UDDF_XSD` at a local copy of `uddf_3.2.0.xsd` to run it.
+    #[test]
+    fn validates_against_uddf_schema() {
+        let Some(xsd) = std::env::var_os("UDDF_XSD") else {
+            eprintln!("UDDF_XSD not set, skipping schema validation");
+            return;
+        };
+        let file = std::env::temp_dir().join(format!("sirius-uddf-{}.uddf", std::process::id()));
+        std::fs::write(&file, dives_to_uddf(&logbook())).unwrap();
+        let output = std::process::Command::new("xmllint")
+            .arg("--noout")
+            .arg("--schema")
+            .arg(&xsd)
+            .arg(&file)
+            .output()
+            .expect("xmllint not found");
+        let _ = std::fs::remove_file(&file);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use roxmltree::Node;
+
+/// Minimal indented XML writer for the XML-based export formats.
+pub struct XmlWriter {
+    out: String,
+    stack: Vec<&'static str>,
+}
+
+impl XmlWriter {
+    pub fn new() -> Self {
+        XmlWriter {
+            out: String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Start an element that will contain children; end it with `close`.
+    pub fn open(&mut self, name: &'static str, attrs: &[(&str, &str)]) {
+        self.start_tag(name, attrs);
+        self.out.push_str(">\n");
+        self.stack.push(name);
+    }
+
+    pub fn close(&mut self) {
+        let name = self.stack.pop().expect("close without open");
+        self.indent();
+        self.out.push_str(&format!("</{}>\n", name));
+    }
+
+    /// An element with text content only.
+    pub fn leaf(&mut self, name: &str, attrs: &[(&str, &str)], text: &str) {
+        self.start_tag(name, attrs);
+        self.out.push_str(&format!(">{}</{}>\n", escape(text), name));
+    }
+
+    /// An element without content.
+    pub fn empty(&mut self, name: &str, attrs: &[(&str, &str)]) {
+        self.start_tag(name, attrs);
+        self.out.push_str("/>\n");
+    }
+
+    pub fn finish(self) -> String {
+        assert!(self.stack.is_empty(), "unclosed element {:?}", self.stack);
+        self.out
+    }
+
+    fn start_tag(&mut self, name: &str, attrs: &[(&str, &str)]) {
+        self.indent();
+        self.out.push('<');
+        self.out.push_str(name);
+        for (key, value) in attrs {
+            self.out.push_str(&format!(" {}=\"{}\"", key, escape(value)));
+        }
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.stack.len() {
+            self.out.push_str("  ");
+        }
+    }
+}
+
+/// Escape text for use in XML content and attribute values.
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// First child element of `node` with the given local name.
+pub fn child<'a, 'i>(node: Node<'a, 'i>, name: &str) -> Option<Node<'a, 'i>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+/// Descend through nested child elements, e.g. `&["geography", "address"]`.
+pub fn path<'a, 'i>(node: Node<'a, 'i>, names: &[&str]) -> Option<Node<'a, 'i>> {
+    names.iter().try_fold(node, |n, name| child(n, name))
+}
+
+/// Trimmed text of the child element `name`, if present and non-empty.
+pub fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name)?
+        .text()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+}
+
+pub fn child_f64(node: Node, name: &str) -> Option<f64> {
+    child_text(node, name)?.parse().ok()
+}